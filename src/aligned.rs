@@ -0,0 +1,62 @@
+//! Heap buffers with a given alignment.
+
+use std::alloc::{self, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
+
+/// Zero-initialized byte buffer whose start is aligned to a given boundary, as required by direct
+/// IO (see `Output::file_direct`).
+pub struct AlignedBuf {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFETY: the buffer owns its allocation, like a `Vec<u8>` would.
+unsafe impl Send for AlignedBuf {}
+unsafe impl Sync for AlignedBuf {}
+
+impl AlignedBuf {
+    /// Allocates a buffer of `len` zero bytes, starting at a multiple of `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` isn't a power of two, or if `len` rounded up to `align` overflows.
+    pub fn new(len: usize, align: usize) -> Self {
+        let layout = Layout::from_size_align(len, align).expect("invalid buffer alignment");
+        let ptr = if len == 0 {
+            // Zero-sized allocations aren't allowed, but any aligned address will do.
+            NonNull::new(align as *mut u8).expect("alignment is non-zero")
+        } else {
+            // SAFETY: the layout has a non-zero size.
+            let ptr = unsafe { alloc::alloc_zeroed(layout) };
+            NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+        };
+        AlignedBuf { ptr, layout }
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the pointer is valid for `size` initialized bytes, or dangling for zero bytes.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as in `deref`, and the buffer is borrowed mutably.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        if self.layout.size() > 0 {
+            // SAFETY: the pointer was allocated with this layout.
+            unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
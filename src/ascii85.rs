@@ -0,0 +1,132 @@
+//! Streaming Ascii85 decoding.
+
+use std::io::{self, Read};
+
+use crate::Input;
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid ascii85 data")
+}
+
+/// Where the decoder is relative to the optional `<~` opening delimiter.
+#[derive(Clone, Copy, PartialEq)]
+enum Start {
+    /// Nothing but whitespace read yet.
+    Before,
+    /// A `<` was read first, which is either the start of `<~`, or data.
+    Lt,
+    /// Past the opening delimiter, if any.
+    Data,
+}
+
+/// Reader decoding Ascii85. See `Input::ascii85`.
+pub(crate) struct Decoder {
+    inner: Input,
+    start: Start,
+    group: [u8; 5],
+    group_len: usize,
+    out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Decoder {
+    pub(crate) fn new(inner: Input) -> Self {
+        Decoder {
+            inner,
+            start: Start::Before,
+            group: [0; 5],
+            group_len: 0,
+            out: vec![],
+            pos: 0,
+            done: false,
+        }
+    }
+
+    fn push(&mut self, b: u8) -> io::Result<()> {
+        match self.start {
+            Start::Before if b.is_ascii_whitespace() => return Ok(()),
+            Start::Before if b == b'<' => {
+                self.start = Start::Lt;
+                return Ok(());
+            }
+            Start::Before => self.start = Start::Data,
+            Start::Lt => {
+                self.start = Start::Data;
+                if b == b'~' {
+                    return Ok(());
+                }
+                self.push(b'<')?;
+            }
+            Start::Data => {}
+        }
+        match b {
+            _ if b.is_ascii_whitespace() => {}
+            b'~' => {
+                // Closing delimiter: whatever follows it is ignored.
+                self.end_group()?;
+                self.done = true;
+            }
+            b'z' if self.group_len == 0 => self.out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                self.group[self.group_len] = b;
+                self.group_len += 1;
+                if self.group_len == 5 {
+                    self.end_group()?;
+                }
+            }
+            _ => return Err(invalid()),
+        }
+        Ok(())
+    }
+
+    /// Decodes the current group, which may be partial at the end of the data.
+    fn end_group(&mut self) -> io::Result<()> {
+        match self.group_len {
+            0 => return Ok(()),
+            1 => return Err(invalid()),
+            _ => {}
+        }
+        let value = self.group[..self.group_len]
+            .iter()
+            .chain(std::iter::repeat(&b'u'))
+            .take(5)
+            .fold(0u64, |acc, &c| acc * 85 + u64::from(c - b'!'));
+        if value > u64::from(u32::MAX) {
+            return Err(invalid());
+        }
+        self.out
+            .extend_from_slice(&(value as u32).to_be_bytes()[..self.group_len - 1]);
+        self.group_len = 0;
+        Ok(())
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 1024];
+        while self.pos == self.out.len() && !self.done {
+            self.out.clear();
+            self.pos = 0;
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                if self.start == Start::Lt {
+                    self.start = Start::Data;
+                    self.push(b'<')?;
+                }
+                self.end_group()?;
+                self.done = true;
+            }
+            for &b in &chunk[..n] {
+                if self.done {
+                    break;
+                }
+                self.push(b)?;
+            }
+        }
+        let n = (self.out.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,111 @@
+//! Minimal base64 (standard alphabet) decoding.
+
+use std::io::{self, Read};
+
+use crate::Input;
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid base64 data")
+}
+
+fn value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some(u32::from(c - b'A')),
+        b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+        b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Incremental decoding state, fed one character at a time.
+#[derive(Default)]
+struct State {
+    acc: u32,
+    bits: u32,
+    /// Whether padding was reached, after which only more padding may follow.
+    padded: bool,
+}
+
+impl State {
+    fn push(&mut self, c: u8, out: &mut Vec<u8>) -> io::Result<()> {
+        if c == b'=' {
+            self.padded = true;
+            return Ok(());
+        }
+        if self.padded {
+            return Err(invalid());
+        }
+        self.acc = (self.acc << 6) | value(c).ok_or_else(invalid)?;
+        self.bits += 6;
+        if self.bits >= 8 {
+            self.bits -= 8;
+            out.push((self.acc >> self.bits) as u8);
+            self.acc &= (1 << self.bits) - 1;
+        }
+        Ok(())
+    }
+
+    /// Checks that the data didn't end in the middle of a byte.
+    fn finish(&self) -> io::Result<()> {
+        if self.bits >= 6 {
+            return Err(invalid());
+        }
+        Ok(())
+    }
+}
+
+/// Decodes base64 text, with or without its trailing padding.
+pub(crate) fn decode(text: &str) -> io::Result<Vec<u8>> {
+    let mut state = State::default();
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for c in text.bytes() {
+        state.push(c, &mut out)?;
+    }
+    state.finish()?;
+    Ok(out)
+}
+
+/// Reader decoding base64. See `Input::base64`.
+pub(crate) struct Decoder {
+    inner: Input,
+    state: State,
+    out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Decoder {
+    pub(crate) fn new(inner: Input) -> Self {
+        Decoder {
+            inner,
+            state: State::default(),
+            out: vec![],
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 1024];
+        while self.pos == self.out.len() && !self.done {
+            self.out.clear();
+            self.pos = 0;
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                self.state.finish()?;
+                self.done = true;
+            }
+            for &c in chunk[..n].iter().filter(|c| !c.is_ascii_whitespace()) {
+                self.state.push(c, &mut self.out)?;
+            }
+        }
+        let n = (self.out.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
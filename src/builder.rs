@@ -0,0 +1,160 @@
+//! Builders gathering the file opening options in a single place.
+
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{filesystem, temp, writers, Input, Output};
+
+/// Builder for an Input over a file, with chainable options. See `Input::builder`.
+#[derive(Debug, Clone)]
+pub struct InputBuilder {
+    path: PathBuf,
+    buffered: Option<usize>,
+    no_follow: bool,
+}
+
+impl InputBuilder {
+    pub(crate) fn new(path: &Path) -> Self {
+        InputBuilder { path: path.to_path_buf(), buffered: None, no_follow: false }
+    }
+
+    /// Buffers reads, `cap` bytes at a time.
+    pub fn buffered(mut self, cap: usize) -> Self {
+        self.buffered = Some(cap);
+        self
+    }
+
+    /// Refuses to follow the last component of the path if it is a symbolic link, as with
+    /// `Input::file_no_follow`. Opening fails with an `Unsupported` error outside of Unix.
+    pub fn no_follow(mut self, no_follow: bool) -> Self {
+        self.no_follow = no_follow;
+        self
+    }
+
+    /// Opens the file with the options set so far.
+    pub fn open(self) -> io::Result<Input> {
+        let mut options = fs::OpenOptions::new();
+        options.read(true);
+        if self.no_follow {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(crate::sys::o_nofollow()?);
+            }
+            #[cfg(not(unix))]
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "cannot refuse symlinks"));
+        }
+        let file = Input::File(filesystem::reject_dir(options.open(&self.path)?)?);
+        Ok(match self.buffered {
+            Some(cap) => Input::Buffered(Box::new(BufReader::with_capacity(cap, file))),
+            None => file,
+        })
+    }
+}
+
+/// Builder for an Output over a file, with chainable options. See `Output::builder`.
+#[derive(Debug, Clone)]
+pub struct OutputBuilder {
+    path: PathBuf,
+    append: bool,
+    truncate: bool,
+    create_dirs: bool,
+    mode: Option<u32>,
+    buffered: Option<usize>,
+    atomic: bool,
+}
+
+impl OutputBuilder {
+    pub(crate) fn new(path: &Path) -> Self {
+        OutputBuilder {
+            path: path.to_path_buf(),
+            append: false,
+            truncate: false,
+            create_dirs: false,
+            mode: None,
+            buffered: None,
+            atomic: false,
+        }
+    }
+
+    /// Appends to the file instead of writing over it from the start. Cannot be combined with
+    /// `atomic`.
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Truncates the file when opening it. By default, the file contents are kept and written over
+    /// from the start, as with `Output::file`. Ignored when appending or writing atomically.
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Creates the missing parent directories of the file.
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Sets the permissions of the file, if it gets created. Ignored outside of Unix.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Buffers writes up to `cap` bytes, as with `Output::smart_buffered`.
+    pub fn buffered(mut self, cap: usize) -> Self {
+        self.buffered = Some(cap);
+        self
+    }
+
+    /// Writes into a temporary file next to the actual one, which is only renamed over it once
+    /// the Output is finished. The file is then either left untouched, or fully written. If the
+    /// Output is dropped without being finished, the temporary file is removed.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Opens the file with the options set so far.
+    pub fn open(self) -> io::Result<Output> {
+        if self.append && self.atomic {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot append atomically"));
+        }
+        if self.create_dirs {
+            if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let out = if self.atomic {
+            let tmp_path = self.path.with_file_name(temp::random_name());
+            let file = self.options().create_new(true).open(&tmp_path)?;
+            Output::Adapter(Box::new(writers::Atomic::new(Output::File(file), tmp_path, self.path)))
+        } else {
+            let mut options = self.options();
+            if self.append {
+                options.append(true);
+            } else {
+                options.truncate(self.truncate);
+            }
+            Output::File(options.create(true).open(&self.path)?)
+        };
+        Ok(match self.buffered {
+            Some(cap) => out.smart_buffered(cap, cap),
+            None => out,
+        })
+    }
+
+    fn options(&self) -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        options.write(true);
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+        options
+    }
+}
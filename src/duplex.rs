@@ -0,0 +1,119 @@
+//! Duplex adapters backing the `InputOutput` wrapping methods.
+
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::InputOutput;
+
+/// Readable and writable stream, which can be boxed into `InputOutput::Custom`.
+pub trait Duplex: Read + Write {}
+
+impl<T: Read + Write + ?Sized> Duplex for T {}
+
+/// Bytes read ahead from an InputOutput by `InputOutput::read_line_nonblocking`, and not consumed
+/// yet, along with that InputOutput.
+pub struct ReadAhead {
+    pub(crate) pending: Vec<u8>,
+    pub(crate) inner: Box<InputOutput>,
+}
+
+/// Policy for retrying transient errors. See `InputOutput::with_retry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts for each operation, including the first one.
+    pub max_attempts: u32,
+    /// Time to wait between two attempts.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns a policy making at most `max_attempts` attempts, waiting `backoff` between each.
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        RetryPolicy { max_attempts, backoff }
+    }
+
+    fn run<T>(&self, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(ref e) if is_transient(e) && attempt < self.max_attempts => {
+                    thread::sleep(self.backoff);
+                    attempt += 1;
+                }
+                ret => return ret,
+            }
+        }
+    }
+}
+
+fn is_transient(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+/// Duplex retrying transient errors following a `RetryPolicy`.
+pub(crate) struct Retry {
+    inner: InputOutput,
+    policy: RetryPolicy,
+}
+
+impl Retry {
+    pub(crate) fn new(inner: InputOutput, policy: RetryPolicy) -> Self {
+        Retry { inner, policy }
+    }
+}
+
+impl Read for Retry {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        self.policy.run(|| inner.read(buf))
+    }
+}
+
+impl Write for Retry {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        self.policy.run(|| inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+        self.policy.run(|| inner.flush())
+    }
+}
+
+/// Duplex passing the bytes actually read and written to callbacks.
+pub(crate) struct Trace<R, W> {
+    inner: InputOutput,
+    on_read: R,
+    on_write: W,
+}
+
+impl<R, W> Trace<R, W> {
+    pub(crate) fn new(inner: InputOutput, on_read: R, on_write: W) -> Self {
+        Trace { inner, on_read, on_write }
+    }
+}
+
+impl<R: FnMut(&[u8]), W> Read for Trace<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.on_read)(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R, W: FnMut(&[u8])> Write for Trace<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        (self.on_write)(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,111 @@
+//! Transcoding of text inputs into UTF-8.
+
+use std::io::{self, Read};
+
+use crate::Input;
+
+/// Text encoding of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    /// Parses an encoding label, case-insensitively.
+    pub(crate) fn from_label(label: &str) -> io::Result<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown encoding: {}", label),
+            )),
+        }
+    }
+}
+
+/// Reader decoding text in a given encoding into UTF-8. See `Input::transcode`.
+pub(crate) struct Transcode {
+    inner: Input,
+    encoding: Encoding,
+    /// Bytes read which don't form a full code unit yet.
+    partial: Vec<u8>,
+    /// High surrogate waiting for its low surrogate.
+    high: Option<u16>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl Transcode {
+    pub(crate) fn new(inner: Input, encoding: Encoding) -> Self {
+        Transcode { inner, encoding, partial: vec![], high: None, out: vec![], pos: 0 }
+    }
+
+    fn push_char(&mut self, c: char) {
+        let mut utf8 = [0; 4];
+        self.out.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+    }
+
+    fn push_unit(&mut self, unit: u16) -> io::Result<()> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-16 data");
+        match (self.high.take(), unit) {
+            (None, 0xD800..=0xDBFF) => self.high = Some(unit),
+            (None, 0xDC00..=0xDFFF) => return Err(invalid()),
+            (None, _) => self.push_char(char::from_u32(u32::from(unit)).ok_or_else(invalid)?),
+            (Some(high), 0xDC00..=0xDFFF) => {
+                let c = 0x10000 + (u32::from(high - 0xD800) << 10) + u32::from(unit - 0xDC00);
+                self.push_char(char::from_u32(c).ok_or_else(invalid)?);
+            }
+            (Some(_), _) => return Err(invalid()),
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self, data: &[u8]) -> io::Result<()> {
+        match self.encoding {
+            Encoding::Utf8 => self.out.extend_from_slice(data),
+            Encoding::Latin1 => data.iter().for_each(|&b| self.push_char(char::from(b))),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                self.partial.extend_from_slice(data);
+                let units = self.partial.len() / 2;
+                for i in 0..units {
+                    let bytes = [self.partial[2 * i], self.partial[2 * i + 1]];
+                    let unit = match self.encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes(bytes),
+                        _ => u16::from_be_bytes(bytes),
+                    };
+                    self.push_unit(unit)?;
+                }
+                self.partial.drain(..2 * units);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for Transcode {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 4096];
+        while self.pos == self.out.len() {
+            self.out.clear();
+            self.pos = 0;
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                if !self.partial.is_empty() || self.high.is_some() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated UTF-16 data"));
+                }
+                return Ok(0);
+            }
+            self.decode(&chunk[..n])?;
+        }
+        let n = (self.out.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,104 @@
+//! Typed integer reading and writing, either fixed-width in either byte order, or as LEB128
+//! varints.
+
+use std::io::{self, Read, Write};
+
+use crate::{Input, Output};
+
+macro_rules! read_ints {
+    ($($ty:ident: $le:ident, $be:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a little-endian `", stringify!($ty), "`.")]
+            pub fn $le(&mut self) -> io::Result<$ty> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                self.read_exact(&mut bytes)?;
+                Ok($ty::from_le_bytes(bytes))
+            }
+
+            #[doc = concat!("Reads a big-endian `", stringify!($ty), "`.")]
+            pub fn $be(&mut self) -> io::Result<$ty> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                self.read_exact(&mut bytes)?;
+                Ok($ty::from_be_bytes(bytes))
+            }
+        )*
+    };
+}
+
+macro_rules! write_ints {
+    ($($ty:ident: $le:ident, $be:ident;)*) => {
+        $(
+            #[doc = concat!("Writes a little-endian `", stringify!($ty), "`.")]
+            pub fn $le(&mut self, v: $ty) -> io::Result<()> {
+                self.write_all(&v.to_le_bytes())
+            }
+
+            #[doc = concat!("Writes a big-endian `", stringify!($ty), "`.")]
+            pub fn $be(&mut self, v: $ty) -> io::Result<()> {
+                self.write_all(&v.to_be_bytes())
+            }
+        )*
+    };
+}
+
+impl Input {
+    read_ints! {
+        u16: read_u16_le, read_u16_be;
+        u32: read_u32_le, read_u32_be;
+        u64: read_u64_le, read_u64_be;
+        i16: read_i16_le, read_i16_be;
+        i32: read_i32_le, read_i32_be;
+        i64: read_i64_le, read_i64_be;
+    }
+
+    /// Reads an unsigned LEB128 varint, as written by `Output::write_varint`.
+    ///
+    /// Varints longer than the 10 bytes needed for a `u64`, or overflowing it, are rejected with an
+    /// `InvalidData` error.
+    pub fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for i in 0..10 {
+            let mut byte = [0];
+            self.read_exact(&mut byte)?;
+            let bits = u64::from(byte[0] & 0x7F);
+            if i == 9 && bits > 1 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "varint overflows u64"));
+            }
+            value |= bits << (7 * i);
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "varint longer than 10 bytes"))
+    }
+}
+
+impl Output {
+    write_ints! {
+        u16: write_u16_le, write_u16_be;
+        u32: write_u32_le, write_u32_be;
+        u64: write_u64_le, write_u64_be;
+        i16: write_i16_le, write_i16_be;
+        i32: write_i32_le, write_i32_be;
+        i64: write_i64_le, write_i64_be;
+    }
+
+    /// Writes `v` as an unsigned LEB128 varint: 7 bits per byte, least significant first, with the
+    /// high bit set on every byte but the last.
+    pub fn write_varint(&mut self, mut v: u64) -> io::Result<()> {
+        let mut bytes = [0; 10];
+        let mut len = 0;
+        loop {
+            let byte = (v & 0x7F) as u8;
+            v >>= 7;
+            if v == 0 {
+                bytes[len] = byte;
+                len += 1;
+                break;
+            }
+            bytes[len] = byte | 0x80;
+            len += 1;
+        }
+        self.write_all(&bytes[..len])
+    }
+}
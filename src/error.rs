@@ -0,0 +1,68 @@
+//! Structured buffer errors.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Error telling apart failing to open a buffer from failing to use it.
+#[derive(Debug)]
+pub enum BufferError {
+    /// The buffer at `path` couldn't be opened.
+    Open { path: PathBuf, source: io::Error },
+    /// Reading from the buffer failed. See `Input::read_to_end_checked`.
+    Read { source: io::Error },
+    /// Writing to the buffer failed. See `Output::write_all_checked`.
+    Write { source: io::Error },
+}
+
+impl BufferError {
+    /// Returns the underlying IO error.
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            BufferError::Open { source, .. }
+            | BufferError::Read { source }
+            | BufferError::Write { source } => source,
+        }
+    }
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferError::Open { path, source } => {
+                write!(f, "cannot open {}: {}", path.display(), source)
+            }
+            BufferError::Read { source } => write!(f, "read failed: {}", source),
+            BufferError::Write { source } => write!(f, "write failed: {}", source),
+        }
+    }
+}
+
+impl Error for BufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+impl From<BufferError> for io::Error {
+    /// Wraps the error into an IO error of the same kind as the underlying one.
+    fn from(err: BufferError) -> Self {
+        io::Error::new(err.io_error().kind(), err)
+    }
+}
+
+/// Error returned by reads on a cancelled Input. See `Input::with_cancel`.
+///
+/// It is wrapped into an `io::Error` of kind `Other`, and can be told apart with
+/// `err.get_ref().map_or(false, |e| e.is::<Cancelled>())`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("read cancelled")
+    }
+}
+
+impl Error for Cancelled {}
@@ -0,0 +1,108 @@
+//! Pluggable filesystems for opening file buffers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::writers::WithKind;
+use crate::{BufferKind, Input, Output};
+
+/// Filesystem used to open file buffers, allowing to redirect them away from the disk.
+pub trait FileSystem {
+    /// Opens the file at the given path for reading.
+    fn open_read(&self, path: &Path) -> io::Result<Input>;
+
+    /// Opens the file at the given path for writing, creating it if it doesn't exist.
+    fn open_write(&self, path: &Path) -> io::Result<Output>;
+}
+
+/// The actual filesystem, used by the `file` constructors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn open_read(&self, path: &Path) -> io::Result<Input> {
+        reject_dir(fs::OpenOptions::new().read(true).open(path)?).map(Input::File)
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Output> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map(Output::File)
+    }
+}
+
+type Files = Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>;
+
+/// In-memory filesystem. Clones share the same files.
+///
+/// Files opened for reading get a snapshot of the file contents at the time of opening, while
+/// files opened for writing update the shared contents as they are written to. Like `RealFs`,
+/// opening a file for writing keeps its contents, and writes start over them from the start.
+#[derive(Debug, Clone, Default)]
+pub struct MemFs {
+    files: Files,
+}
+
+impl MemFs {
+    /// Returns an empty in-memory filesystem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileSystem for MemFs {
+    fn open_read(&self, path: &Path) -> io::Result<Input> {
+        let files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files
+            .get(path)
+            .map(|data| Input::memory_from(data.clone()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Output> {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files.entry(path.to_path_buf()).or_default();
+        let file = MemFile { files: self.files.clone(), path: path.to_path_buf(), pos: 0 };
+        Ok(Output::Adapter(Box::new(WithKind::new(file, BufferKind::File))))
+    }
+}
+
+/// Returns an error if the opened file is a directory. Some platforms happily open directories,
+/// only to fail on the first read.
+pub(crate) fn reject_dir(file: fs::File) -> io::Result<fs::File> {
+    if file.metadata()?.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "path is a directory"));
+    }
+    Ok(file)
+}
+
+/// File of a `MemFs`, opened for writing.
+struct MemFile {
+    files: Files,
+    path: PathBuf,
+    pos: usize,
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let data = files.entry(self.path.clone()).or_default();
+        let end = self.pos + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[self.pos..end].copy_from_slice(buf);
+        self.pos = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@
 //!
 //! # Example
 //!
-//! ```rust
+//! ```rust,ignore
 //! use clap::{App, Arg};
 //! use wbuf::{Input, Output};
 //! let matches = App::new("app")
@@ -24,65 +24,830 @@
 //! let mut output_buf = Output::from_arg(matches.value_of("output"));
 //! parse_input(&mut input_buf).and_then(|ast| transpile(ast, &mut output_buf));
 //! ```
+//!
+//! # `no_std`
+//!
+//! Everything above requires the `std` feature, enabled by default. Without it the crate is
+//! `no_std`, and only provides the `alloc`-only memory buffers of the `memory` module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::{fs, io, mem};
+#[cfg(feature = "std")]
+use std::ffi::OsStr;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use std::io::{BufRead, BufReader, Cursor, Error, Read, Seek, SeekFrom, Write};
+
+#[cfg(feature = "std")]
+use duplex::ReadAhead;
+
+#[cfg(feature = "std")]
+mod aligned;
+#[cfg(feature = "ascii85")]
+mod ascii85;
+#[cfg(feature = "std")]
+mod base64;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+mod crc32c;
+#[cfg(feature = "std")]
+mod duplex;
+#[cfg(feature = "std")]
+mod encoding;
+#[cfg(feature = "std")]
+mod endian;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+mod filesystem;
+#[cfg(feature = "std")]
+mod hash;
+#[cfg(feature = "std")]
+mod newline;
+#[cfg(feature = "std")]
+mod readers;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+mod spec;
+#[cfg(all(feature = "std", unix))]
+mod sys;
+#[cfg(all(feature = "std", windows))]
+mod sys_windows;
+#[cfg(feature = "std")]
+mod temp;
+#[cfg(all(feature = "std", unix))]
+mod terminal;
+#[cfg(feature = "std")]
+mod writers;
+
+pub mod memory;
 
-use std::{fs, io};
-use std::io::{Cursor, Error, Read, Write};
+#[cfg(feature = "std")]
+pub use aligned::AlignedBuf;
+#[cfg(feature = "std")]
+pub use builder::{InputBuilder, OutputBuilder};
+#[cfg(feature = "std")]
+pub use duplex::{Duplex, RetryPolicy};
+#[cfg(feature = "std")]
+pub use error::{BufferError, Cancelled};
+#[cfg(feature = "std")]
+pub use filesystem::{FileSystem, MemFs, RealFs};
+#[cfg(feature = "std")]
+pub use hash::{HashAlgorithm, HashHandle};
+#[cfg(feature = "std")]
+pub use newline::{LineTerminator, LinesAuto, LinesWithTerminators};
+#[cfg(feature = "std")]
+pub use readers::{Chunks, EofBehavior, HashingInput, StickyInput};
+#[cfg(feature = "std")]
+pub use shared::SharedOutput;
+#[cfg(feature = "std")]
+pub use spec::BufferSpec;
+#[cfg(all(feature = "std", unix))]
+pub use terminal::TerminalGuard;
+#[cfg(feature = "std")]
+pub use writers::{BufferedOutput, CaptureHandle, OutputAdapter};
 
-/// Input buffer wrapper type. Wraps stdin, a read-only memory Cursor, or a readable file buffer.
+/// Largest payload accepted by `Output::write_frame` and `Input::read_frame`, 16 MiB.
+#[cfg(feature = "std")]
+pub const MAX_FRAME_LEN: usize = 16 << 20;
+
+/// Input buffer wrapper type. Wraps stdin, a read-only memory Cursor, a readable file buffer, a
+/// buffered input, a decoder keeping its first error, a hashing input, or any other boxed reader.
+#[cfg(feature = "std")]
 pub enum Input {
     Standard(io::Stdin),
     Memory(io::Cursor<Vec<u8>>),
     File(fs::File),
+    Buffered(Box<BufReader<Input>>),
+    Sticky(Box<StickyInput>),
+    Hashing(Box<HashingInput>),
+    Custom(Box<dyn Read + Send>),
 }
 
-/// Output buffer wrapper type. Wraps stdout, a write-only memory Cursor, or a writeable file buffer.
+/// Output buffer wrapper type. Wraps stdout or stderr, a write-only memory Cursor, a writeable file
+/// buffer, a buffered output, an adapter over another output, or any other boxed writer.
+#[cfg(feature = "std")]
 pub enum Output {
     Standard(io::Stdout),
+    StandardError(io::Stderr),
     Memory(io::Cursor<Vec<u8>>),
     File(fs::File),
+    Buffered(Box<BufferedOutput>),
+    Adapter(Box<dyn OutputAdapter>),
+    Custom(Box<dyn Write + Send>),
 }
 
-/// Duplex I/O buffer wrapper type. Wraps stdin/stdout, a read/write Cursor, or a readable/writable
-/// file buffer.
+/// Duplex I/O buffer wrapper type. Wraps stdin/stdout, a read/write Cursor, a readable/writable
+/// file buffer, or any other boxed duplex stream.
+#[cfg(feature = "std")]
 pub enum InputOutput {
     Standard(io::Stdin, io::Stdout),
     Memory(io::Cursor<Vec<u8>>),
     File(fs::File),
+    /// Internal state of `read_line_nonblocking`: bytes already read from the inner buffer but not
+    /// yet consumed, followed by the inner buffer. Can't be built outside of this crate.
+    #[doc(hidden)]
+    Pending(ReadAhead),
+    Custom(Box<dyn Duplex + Send>),
+}
+
+/// Kind of store backing a buffer. See `Input::kind`, `Output::kind` and `InputOutput::kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferKind {
+    /// Stdin, stdout, or both.
+    Standard,
+    /// Memory cursor.
+    Memory,
+    /// File.
+    File,
+    /// Boxed reader, writer or duplex stream.
+    Custom,
 }
 
+#[cfg(feature = "std")]
 impl Input {
     /// Returns an Input wrapping stdin.
     pub fn stdin() -> Self {
         Input::Standard(io::stdin())
     }
 
+    /// Returns an Input wrapping stdin, after checking that stdin is actually open.
+    ///
+    /// Some CI and container setups start programs with stdin closed, making reads fail obscurely
+    /// or, if the descriptor gets reused, read from an unrelated file. This fails fast instead,
+    /// with a `NotConnected` error. The check is only done on Unix.
+    ///
+    /// Note that the Rust runtime reopens stdin on `/dev/null` if it is closed when the program
+    /// starts, in which case stdin is open, and reads at end of file.
+    pub fn stdin_checked() -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            if !sys::is_open(io::stdin().as_raw_fd()) {
+                return Err(io::Error::new(io::ErrorKind::NotConnected, "stdin is closed"));
+            }
+        }
+        Ok(Self::stdin())
+    }
+
+    /// Returns an Input wrapping stdin, unless stdin is an interactive terminal.
+    ///
+    /// This allows tools to only read stdin when data is piped or redirected into them, instead of
+    /// waiting for the user to type something. When `None` is returned, callers can e.g. print a
+    /// prompt or usage information instead.
+    pub fn stdin_if_piped() -> Option<Self> {
+        use std::io::IsTerminal;
+        let stdin = io::stdin();
+        if stdin.is_terminal() {
+            None
+        } else {
+            Some(Input::Standard(stdin))
+        }
+    }
+
     /// Returns an Input wrapping a Cursor.
     pub fn memory() -> Self {
         Input::Memory(Cursor::new(vec![]))
     }
 
-    /// Returns an Input wrapping a file.
-    pub fn file(path: &str) -> io::Result<Self> {
-        fs::OpenOptions::new()
+    /// Returns an Input wrapping a Cursor over the given data.
+    pub fn memory_from<D: Into<Vec<u8>>>(data: D) -> Self {
+        Input::Memory(Cursor::new(data.into()))
+    }
+
+    /// Returns an Input over the given data which, once exhausted, behaves following `eof`
+    /// instead of returning end of file. Useful to simulate a stalled stream in tests.
+    pub fn memory_from_with_eof<D: Into<Vec<u8>>>(data: D, eof: EofBehavior) -> Self {
+        match eof {
+            EofBehavior::Eof => Self::memory_from(data),
+            eof => Input::Custom(Box::new(readers::MemoryWithEof::new(data.into(), eof))),
+        }
+    }
+
+    /// Returns an Input wrapping a file. Opening a directory fails with an `InvalidInput` error.
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::file_in(&RealFs, path)
+    }
+
+    /// Returns an Input wrapping a file opened from the given filesystem.
+    pub fn file_in<P: AsRef<Path>>(fs: &dyn FileSystem, path: P) -> io::Result<Self> {
+        fs.open_read(path.as_ref())
+    }
+
+    /// Returns an Input wrapping a file, refusing to follow the last component of the path if it
+    /// is a symbolic link (`O_NOFOLLOW`). Opening a symlink then fails with `ELOOP`.
+    ///
+    /// Only available on Unix: Windows has no such flag, and only follows symbolic links when
+    /// opening. Returns an `Unsupported` error on the Unix platforms and architectures the value of
+    /// `O_NOFOLLOW` isn't known for.
+    #[cfg(unix)]
+    pub fn file_no_follow<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(sys::o_nofollow()?)
+            .open(path.as_ref())?;
+        filesystem::reject_dir(file).map(Input::File)
+    }
+
+    /// Returns an Input reading a file with direct IO (`O_DIRECT`), bypassing the page cache.
+    ///
+    /// Direct IO has alignment requirements, usually to the logical block size of the device (512
+    /// or 4096 bytes): the buffers read into, their length and the file offsets must all be
+    /// aligned, otherwise reads fail with `EINVAL`. Use `AlignedBuf` for the buffers, and avoid
+    /// wrappers reading with buffers of their own.
+    ///
+    /// Returns an `Unsupported` error on the architectures the value of `O_DIRECT` isn't known for.
+    #[cfg(target_os = "linux")]
+    pub fn file_direct<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let file = fs::OpenOptions::new()
             .read(true)
-            .open(path)
-            .map(Input::File)
+            .custom_flags(sys::o_direct()?)
+            .open(path.as_ref())?;
+        filesystem::reject_dir(file).map(Input::File)
+    }
+
+    /// Returns a builder for an Input over the file at `path`, to set opening options like
+    /// buffering or refusing symlinks in one place.
+    pub fn builder<P: AsRef<Path>>(path: P) -> InputBuilder {
+        InputBuilder::new(path.as_ref())
+    }
+
+    /// Opens the file twice, returning an Input over the bytes before `offset` and another over the
+    /// bytes from `offset` to the end. Both can then be read independently, e.g. from different
+    /// threads.
+    pub fn split_at<P: AsRef<Path>>(path: P, offset: u64) -> io::Result<(Input, Input)> {
+        let head = fs::File::open(path.as_ref())?;
+        let mut tail = fs::File::open(path.as_ref())?;
+        tail.seek(SeekFrom::Start(offset))?;
+        Ok((Input::Custom(Box::new(head.take(offset))), Input::File(tail)))
     }
 
     /// Returns either a wrapped file buffer, or stdin, depending on the argument passed in.
     ///
     /// The function selects the buffer following these rules:
     /// - No value, or the a literal "-" returns stdin.
+    /// - On Unix, a file descriptor number, either bare ("3") or as "/dev/fd/3", returns a wrapped
+    ///   file buffer over a duplicate of that already-open descriptor, which is left open. This
+    ///   shadows a file with the same name, which can still be opened as "./3". The standard
+    ///   descriptors 0, 1 and 2, and numbers which aren't open descriptors, are opened as paths.
     /// - Any other value returns a wrapped file buffer. The file is opened with std::fs::OpenOptions,
-    ///  therefore the file is required to exist and be readable for the operation to succeed.
+    ///   therefore the file is required to exist and be readable for the operation to succeed. A
+    ///   directory is rejected with an `InvalidInput` error.
+    ///
+    /// Arguments found after a `--` separator should go through `from_arg_literal` instead, so that
+    /// a bare "-" opens a file named "-".
     pub fn from_arg(arg: Option<&str>) -> io::Result<Self> {
         match arg {
             None | Some("-") => Ok(Self::stdin()),
-            Some(fname) => Self::file(fname),
+            Some(fname) => {
+                #[cfg(unix)]
+                if let Some(file) = file_from_fd_arg(fname) {
+                    return file.map(Input::File);
+                }
+                Self::file(fname)
+            }
+        }
+    }
+
+    /// Returns a wrapped file buffer over the file at `arg`, taken literally: "-" is a file named
+    /// "-" rather than stdin, and file descriptor numbers are file names as well. Meant for
+    /// arguments found after a `--` separator.
+    pub fn from_arg_literal(arg: &str) -> io::Result<Self> {
+        Self::file(arg)
+    }
+
+    /// Same as `from_arg`, but failing to open the buffer returns a `BufferError::Open` carrying
+    /// the argument as its path.
+    pub fn from_arg_checked(arg: Option<&str>) -> Result<Self, BufferError> {
+        Self::from_arg(arg).map_err(|source| BufferError::Open {
+            path: PathBuf::from(arg.unwrap_or("-")),
+            source,
+        })
+    }
+
+    /// Same as `from_arg`, but taking a raw OS string, as given by `std::env::args_os`. Arguments
+    /// which aren't valid UTF-8 are opened as paths as-is, without any lossy conversion.
+    pub fn from_arg_os(arg: Option<&OsStr>) -> io::Result<Self> {
+        match arg.map(|arg| arg.to_str().ok_or(arg)) {
+            None => Self::from_arg(None),
+            Some(Ok(arg)) => Self::from_arg(Some(arg)),
+            Some(Err(path)) => Self::file(path),
+        }
+    }
+
+    /// Returns the buffer described by the argument, like `from_arg`, with support for inline
+    /// data and encodings.
+    ///
+    /// On top of the `from_arg` rules:
+    /// - A value starting with "data:base64," returns a memory buffer with the base64-decoded rest
+    ///   of the value.
+    /// - Any other value starting with "data:" returns a memory buffer with the rest of the value.
+    /// - A value naming an existing path returns the buffer described by it, as `from_arg` does,
+    ///   even if it contains a "#".
+    /// - Any other value ending with "#" followed by an encoding label, made of ASCII letters,
+    ///   digits, "-" and "_" (e.g. "file.txt#utf-16le"), returns the buffer described by what comes
+    ///   before the "#", transcoded into UTF-8 (see `transcode`). Unknown encodings fail with an
+    ///   `InvalidInput` error.
+    pub fn from_arg_ext(arg: Option<&str>) -> io::Result<Self> {
+        match arg {
+            Some(arg) if arg.starts_with("data:base64,") => {
+                base64::decode(&arg["data:base64,".len()..]).map(Self::memory_from)
+            }
+            Some(arg) if arg.starts_with("data:") => Ok(Self::memory_from(&arg["data:".len()..])),
+            Some(arg) if Path::new(arg).exists() => Self::from_arg(Some(arg)),
+            Some(arg) => match arg.rsplit_once('#') {
+                Some((path, label)) if is_encoding_label(label) => {
+                    let encoding = encoding::Encoding::from_label(label)?;
+                    Ok(Self::from_arg(Some(path))?.transcode_with(encoding))
+                }
+                _ => Self::from_arg(Some(arg)),
+            },
+            None => Self::from_arg(None),
+        }
+    }
+
+    /// Returns an Input decoding the text read from this Input from the given encoding into
+    /// UTF-8.
+    ///
+    /// Supported labels are "utf-8", "utf-16le", "utf-16be" and "latin1" (or "iso-8859-1"),
+    /// matched case-insensitively. Unknown encodings fail with an `InvalidInput` error, and
+    /// invalid text makes reads fail with an `InvalidData` error. The input stays failed after
+    /// such an error, see `take_error`.
+    pub fn transcode(self, encoding: &str) -> io::Result<Input> {
+        encoding::Encoding::from_label(encoding).map(|encoding| self.transcode_with(encoding))
+    }
+
+    fn transcode_with(self, encoding: encoding::Encoding) -> Input {
+        match encoding {
+            encoding::Encoding::Utf8 => self,
+            encoding => {
+                Input::Sticky(Box::new(StickyInput::new(encoding::Transcode::new(self, encoding))))
+            }
+        }
+    }
+
+    /// Reads the rest of the input into a string, erroring with `InvalidData` if it isn't valid
+    /// UTF-8.
+    pub fn read_all_string(&mut self) -> io::Result<String> {
+        let mut data = String::new();
+        self.read_to_string(&mut data)?;
+        Ok(data)
+    }
+
+    /// Same as `read_to_end`, but a failing read returns a `BufferError::Read`, to tell it apart
+    /// from failing to open the input.
+    pub fn read_to_end_checked(&mut self, buf: &mut Vec<u8>) -> Result<usize, BufferError> {
+        self.read_to_end(buf).map_err(|source| BufferError::Read { source })
+    }
+
+    /// Reads the rest of the input into a string, replacing invalid UTF-8 sequences with U+FFFD.
+    pub fn read_all_string_lossy(&mut self) -> io::Result<String> {
+        let mut data = vec![];
+        self.read_to_end(&mut data)?;
+        Ok(String::from_utf8(data)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+    }
+
+    /// Reads a header of exactly `n` bytes, returning it along with the rest of the input.
+    ///
+    /// No byte past the header is read, so the returned Input continues right after it, whatever
+    /// the kind of input. This Input is left empty.
+    pub fn read_header(&mut self, n: usize) -> io::Result<(Vec<u8>, Input)> {
+        let mut header = vec![0; n];
+        self.read_exact(&mut header)?;
+        Ok((header, mem::replace(self, Input::memory())))
+    }
+
+    /// Reads bytes into `buf` until the `delim` sequence is found, delimiter included, or the end
+    /// of the input is reached. Returns the number of bytes appended to `buf`.
+    ///
+    /// Memory and buffered inputs are searched in their buffer. Other inputs are first turned into
+    /// a buffered input over themselves, which they stay: the bytes read past the delimiter are
+    /// kept in the buffer, and returned by the next reads.
+    pub fn read_until_seq(&mut self, delim: &[u8], buf: &mut Vec<u8>) -> io::Result<usize> {
+        if delim.is_empty() {
+            return Ok(0);
+        }
+        if !matches!(self, Input::Memory(_) | Input::Buffered(_)) {
+            let inner = mem::replace(self, Input::memory());
+            *self = Input::Buffered(Box::new(BufReader::new(inner)));
+        }
+        match self {
+            Input::Memory(c) => read_until_seq(c, delim, buf),
+            Input::Buffered(b) => read_until_seq(b.as_mut(), delim, buf),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reads a frame written by `Output::write_frame`: a big-endian `u32` length, followed by that
+    /// many bytes, which are returned.
+    ///
+    /// Lengths above `MAX_FRAME_LEN` are rejected with an `InvalidData` error, without reading the
+    /// payload, so that a corrupted length can't allocate gigabytes. If the input ends in the middle
+    /// of a frame, an `UnexpectedEof` error says how many of the expected bytes were read.
+    pub fn read_frame(&mut self) -> io::Result<Vec<u8>> {
+        let mut len = [0; 4];
+        match read_full(self, &mut len)? {
+            4 => {}
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => return Err(truncated_frame("truncated frame length", n, 4)),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+        }
+        let mut payload = vec![0; len];
+        match read_full(self, &mut payload)? {
+            n if n == len => Ok(payload),
+            n => Err(truncated_frame("truncated frame", n, len)),
+        }
+    }
+
+    /// Reads and discards everything left in the input, returning the number of bytes discarded.
+    ///
+    /// Memory inputs just move to their end. Other inputs are read through a buffer on the stack,
+    /// so nothing is allocated.
+    pub fn drain(&mut self) -> io::Result<u64> {
+        if let Input::Memory(c) = self {
+            let len = c.get_ref().len() as u64;
+            let n = len.saturating_sub(c.position());
+            c.set_position(len.max(c.position()));
+            return Ok(n);
+        }
+        io::copy(self, &mut io::sink())
+    }
+
+    /// Reads everything left in the input, e.g. to inspect the bytes left over after the last
+    /// complete frame.
+    pub fn remaining_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut rest = vec![];
+        self.read_to_end(&mut rest)?;
+        Ok(rest)
+    }
+
+    /// Returns an Input reading from each of `inputs` in turn, one read call each, so that
+    /// none of them starves the others. Inputs are dropped from the rotation once exhausted.
+    ///
+    /// Each read goes to a single input, and blocks if that input blocks.
+    pub fn round_robin(inputs: Vec<Input>) -> Input {
+        Input::Custom(Box::new(readers::RoundRobin::new(inputs)))
+    }
+
+    /// Returns an Input yielding `prefix` first, then the rest of this Input. This allows to push
+    /// back bytes which were already read.
+    pub fn with_prefix(self, prefix: Vec<u8>) -> Input {
+        Input::Custom(Box::new(Cursor::new(prefix).chain(self)))
+    }
+
+    /// Returns whether both buffers are memory buffers with the same contents. Other buffers can't
+    /// be compared, and always return `false`.
+    pub fn content_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Input::Memory(a), Input::Memory(b)) => a.get_ref() == b.get_ref(),
+            _ => false,
+        }
+    }
+
+    /// Reads up to `len` bytes starting at byte `start` of the input, stopping early at the end of
+    /// the input.
+    ///
+    /// Only memory and file inputs can seek to `start`; other inputs return an `Unsupported` error.
+    pub fn read_range(&mut self, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Input::Memory(m) => m.seek(SeekFrom::Start(start))?,
+            Input::File(f) => f.seek(SeekFrom::Start(start))?,
+            _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "input is not seekable")),
+        };
+        let mut data = vec![];
+        self.by_ref().take(len).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Returns an Input passing every byte read from this Input through `f`, e.g. to fold case.
+    pub fn map_bytes<F>(self, f: F) -> Input
+    where
+        F: FnMut(u8) -> u8 + Send + 'static,
+    {
+        Input::Custom(Box::new(readers::MapBytes::new(self, f)))
+    }
+
+    /// Returns an Input stripping the spaces and tabs at the end of every line of this Input, line
+    /// terminators excluded.
+    ///
+    /// Lines are buffered one at a time, so a line is only returned once it has been fully read.
+    pub fn trim_line_ends(self) -> Input {
+        Input::Custom(Box::new(readers::MapLines::new(self, readers::trim_line_end)))
+    }
+
+    /// Returns an Input decoding the base64 data (standard alphabet) read from this Input.
+    ///
+    /// Whitespace is ignored, so that line-wrapped data decodes as well, and the trailing padding
+    /// is optional. Invalid data fails with an `InvalidData` error, after which the input stays
+    /// failed (see `take_error`).
+    pub fn base64(self) -> Input {
+        Input::Sticky(Box::new(StickyInput::new(base64::Decoder::new(self))))
+    }
+
+    /// Returns an Input decoding the Ascii85 data read from this Input.
+    ///
+    /// The data may be enclosed between `<~` and `~>` delimiters, and anything after `~>` is
+    /// ignored. The encoded input is read 1024 bytes at a time though, so bytes past `~>` may
+    /// already have been consumed from it. Whitespace is ignored, and `z` stands for four zero
+    /// bytes. Invalid data fails with an `InvalidData` error, after which the input stays failed
+    /// (see `take_error`). Requires the `ascii85` feature.
+    #[cfg(feature = "ascii85")]
+    pub fn ascii85(self) -> Input {
+        Input::Sticky(Box::new(StickyInput::new(ascii85::Decoder::new(self))))
+    }
+
+    /// Returns an Input hashing every byte read through it, along with a handle giving the digest
+    /// of the bytes read so far, at any point. The digest is also given by `current_digest`, as long
+    /// as the Input isn't wrapped any further.
+    pub fn running_hash(self, algo: HashAlgorithm) -> (Input, HashHandle) {
+        let handle = HashHandle::new(algo);
+        let input = Input::Hashing(Box::new(HashingInput::new(self, handle.clone())));
+        (input, handle)
+    }
+
+    /// Returns the digest of the bytes read so far from a hashing input (see `running_hash`).
+    /// Returns `None` for other inputs.
+    pub fn current_digest(&self) -> Option<Vec<u8>> {
+        match self {
+            Input::Hashing(h) => Some(h.current_digest()),
+            _ => None,
+        }
+    }
+
+    /// Returns an Input checking the CRC32C footer written by `Output::with_crc_footer`.
+    ///
+    /// The footer itself is not returned. Once the end of the input is reached, a read fails with
+    /// an `InvalidData` error if the footer is missing or doesn't match the bytes read before it.
+    pub fn verify_crc_footer(self) -> Input {
+        Input::Custom(Box::new(readers::CrcVerify::new(self)))
+    }
+
+    /// Returns an Input turning every `\r\n` line terminator into `\n`, even when the `\r` and
+    /// the `\n` come from separate reads of this Input. Lone `\r` are left untouched.
+    ///
+    /// Lines are buffered one at a time, so a line is only returned once it has been fully read.
+    pub fn normalize_crlf(self) -> Input {
+        Input::Custom(Box::new(readers::MapLines::new(self, readers::crlf_to_lf)))
+    }
+
+    /// Returns an Input which stops reading once `token` is set, allowing another thread to abort a
+    /// long transfer.
+    ///
+    /// The token is checked before each read from this Input, and reads fail with a `Cancelled`
+    /// error once it is set. `Interrupted` isn't used, as most read loops retry on it.
+    pub fn with_cancel(self, token: Arc<AtomicBool>) -> Input {
+        Input::Custom(Box::new(readers::Cancellable::new(self, token)))
+    }
+
+    /// Returns an Input which stops reading once `deadline` has passed, bounding the total time
+    /// spent reading across all reads.
+    ///
+    /// The deadline is checked before each read from this Input, and reads fail with a `TimedOut`
+    /// error once it has passed. A single read blocking past the deadline isn't interrupted.
+    pub fn with_deadline(self, deadline: Instant) -> Input {
+        Input::Custom(Box::new(readers::Deadline::new(self, deadline)))
+    }
+
+    /// Returns an Input failing with a `TimedOut` error when less than `bytes_per_sec` bytes per
+    /// second were read over the last `window`, e.g. to drop clients trickling bytes in.
+    ///
+    /// The rate is checked after each read, once `window` has passed since this call. The read
+    /// finding the rate too low still returns the bytes it read, and every read after it fails. A
+    /// single read blocking forever isn't interrupted; combine with a read timeout on the
+    /// underlying stream for that.
+    pub fn min_rate(self, bytes_per_sec: u64, window: Duration) -> Input {
+        Input::Custom(Box::new(readers::MinRate::new(self, bytes_per_sec, window)))
+    }
+
+    /// Returns an Input reading at most `n` bytes from this Input on each read call, whatever the
+    /// size of the buffer passed in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn max_read_size(self, n: usize) -> Input {
+        Input::Custom(Box::new(readers::MaxReadSize::new(self, n)))
+    }
+
+    /// Reads into `buf` from the given offset, without moving the current position.
+    ///
+    /// Files use positional reads (`pread`), and memory buffers restore their position after
+    /// reading. Other inputs return an `Unsupported` error.
+    #[cfg(unix)]
+    pub fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        use std::os::unix::fs::FileExt;
+        match self {
+            Input::File(f) => f.read_at(buf, offset),
+            Input::Memory(m) => {
+                let pos = m.position();
+                m.set_position(offset);
+                let ret = m.read(buf);
+                m.set_position(pos);
+                ret
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "input cannot read at an offset")),
+        }
+    }
+
+    /// Returns an iterator over chunks of `size` bytes of the input. The last chunk may be shorter.
+    ///
+    /// When a read fails partway through a chunk, e.g. with `WouldBlock`, the error is returned and
+    /// the bytes read so far are kept: the next call carries on filling the same chunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn chunks(self, size: usize) -> Chunks {
+        Chunks::new(self, size)
+    }
+
+    /// Returns an iterator over the lines of the input, each with the terminator that ended it.
+    ///
+    /// Lines are read through an internal buffer, therefore bytes past the last line returned may
+    /// already have been consumed from the input if the iterator is dropped before the end.
+    pub fn lines_with_terminators(&mut self) -> LinesWithTerminators<'_> {
+        LinesWithTerminators::new(self)
+    }
+
+    /// Returns an iterator over the lines of the input, without their terminators. The terminator
+    /// is detected once, as the first `\n`, `\r\n` or `\r` found, and only that one splits the
+    /// following lines.
+    ///
+    /// Lines are read through an internal buffer, therefore bytes past the last line returned may
+    /// already have been consumed from the input if the iterator is dropped before the end.
+    pub fn lines_auto(&mut self) -> LinesAuto<'_> {
+        LinesAuto::new(self)
+    }
+
+    /// Returns the total length of the input, in bytes.
+    ///
+    /// Stdin and custom readers have no known length, and return an `Unsupported` error.
+    pub fn len(&self) -> io::Result<u64> {
+        match self {
+            Input::Memory(m) => Ok(m.get_ref().len() as u64),
+            Input::File(f) => f.metadata().map(|m| m.len()),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "input has no known length")),
+        }
+    }
+
+    /// Returns the total length of the input, in bytes, measured by seeking to its end, then back
+    /// to where it was. Unlike `len`, this works for block devices, and tells pipes (which return
+    /// `None`) apart from empty files.
+    ///
+    /// Memory inputs return their length. Stdin, custom readers and non-seekable files return
+    /// `None`.
+    pub fn known_length(&mut self) -> Option<u64> {
+        match self {
+            Input::Memory(m) => Some(m.get_ref().len() as u64),
+            Input::File(f) => {
+                let pos = f.stream_position().ok()?;
+                let len = f.seek(SeekFrom::End(0)).ok()?;
+                f.seek(SeekFrom::Start(pos)).ok()?;
+                Some(len)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes not read yet of a memory input, to parse them in place without copying.
+    /// Returns `None` for other inputs.
+    pub fn remaining_slice(&self) -> Option<&[u8]> {
+        match self {
+            Input::Memory(c) => {
+                let data = c.get_ref();
+                Some(&data[(c.position() as usize).min(data.len())..])
+            }
+            _ => None,
+        }
+    }
+
+    /// Saves the settings of the terminal stdin is connected to, returning a guard restoring them
+    /// once dropped. Callers can then change terminal modes, knowing they will be put back even on
+    /// panic.
+    ///
+    /// Only inputs reading from stdin support this, others return an `Unsupported` error. Stdin not
+    /// being a terminal fails with the OS error (`ENOTTY`).
+    #[cfg(unix)]
+    pub fn terminal_guard(&self) -> io::Result<TerminalGuard> {
+        use std::os::unix::io::AsRawFd;
+        match self {
+            Input::Standard(s) => TerminalGuard::new(s.as_raw_fd()),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "input is not stdin")),
+        }
+    }
+
+    /// Returns the kind of store backing this Input.
+    pub fn kind(&self) -> BufferKind {
+        match self {
+            Input::Standard(_) => BufferKind::Standard,
+            Input::Memory(_) => BufferKind::Memory,
+            Input::File(_) => BufferKind::File,
+            Input::Buffered(b) => b.get_ref().kind(),
+            Input::Sticky(_) | Input::Hashing(_) | Input::Custom(_) => BufferKind::Custom,
+        }
+    }
+
+    /// Returns an Input failing with an `InvalidData` "line too long" error once a line runs over
+    /// `limit` bytes without a newline, so that line-reading helpers such as `read_line` don't
+    /// buffer an unbounded line. The terminator doesn't count towards the limit, but a `\r` before
+    /// it does.
+    ///
+    /// The bytes of the line up to the limit are still returned first, and the input stays failed
+    /// afterwards (see `take_error`).
+    pub fn max_line_len(self, limit: usize) -> Input {
+        Input::Sticky(Box::new(StickyInput::new(readers::MaxLineLen::new(self, limit))))
+    }
+
+    /// Returns the error which failed a checking or decoding input (see `base64`, `ascii85`,
+    /// `transcode` and `max_line_len`), clearing it. The input stays failed, and later reads keep
+    /// returning an error. Returns `None` for other inputs, or if no error happened.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        match self {
+            Input::Sticky(s) => s.take_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns the wrapped stdin handle if this Input reads from stdin, to use std-specific APIs
+    /// like `lock`. Returns `None` for other inputs.
+    pub fn as_stdin(&self) -> Option<&io::Stdin> {
+        match self {
+            Input::Standard(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the input is empty. See `len` for details.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Returns the number of bytes that can be read right away without blocking.
+    ///
+    /// Stdin and files are queried with the `FIONREAD` ioctl; memory buffers return the number of
+    /// bytes left after the cursor, and buffered inputs add the bytes left in their buffer to those
+    /// of the input they wrap. Custom readers return an `Unsupported` error, as do stdin and files
+    /// on the platforms the value of `FIONREAD` isn't known for.
+    #[cfg(unix)]
+    pub fn readable_bytes(&self) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+        match self {
+            Input::Standard(s) => sys::readable_bytes(s.as_raw_fd()),
+            Input::Memory(m) => Ok(m.get_ref().len().saturating_sub(m.position() as usize)),
+            Input::File(f) => sys::readable_bytes(f.as_raw_fd()),
+            Input::Buffered(b) => Ok(b.buffer().len() + b.get_ref().readable_bytes()?),
+            Input::Sticky(_) | Input::Hashing(_) | Input::Custom(_) => {
+                Err(io::Error::new(io::ErrorKind::Unsupported, "custom readers cannot be queried"))
+            }
         }
     }
 }
 
+#[cfg(all(feature = "std", target_os = "linux"))]
+impl Input {
+    /// Hints the kernel that the file is going to be read sequentially, so that it reads ahead
+    /// more aggressively. Does nothing for other inputs.
+    pub fn advise_sequential(&self) -> io::Result<()> {
+        self.advise(sys::Advice::Sequential)
+    }
+
+    /// Hints the kernel that the whole file is going to be needed soon, so that it starts reading
+    /// it ahead. Does nothing for other inputs.
+    pub fn advise_willneed(&self) -> io::Result<()> {
+        self.advise(sys::Advice::WillNeed)
+    }
+
+    fn advise(&self, advice: sys::Advice) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        match self {
+            Input::File(f) => sys::fadvise(f.as_raw_fd(), advice),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl Read for Input {
     /// Reads from the underlying buffer.
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -90,52 +855,630 @@ impl Read for Input {
             Input::Standard(ref mut s) => s.read(buf),
             Input::Memory(ref mut m) => m.read(buf),
             Input::File(ref mut f) => f.read(buf),
+            Input::Buffered(ref mut b) => b.read(buf),
+            Input::Sticky(ref mut r) => r.read(buf),
+            Input::Hashing(ref mut r) => r.read(buf),
+            Input::Custom(ref mut r) => r.read(buf),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Output {
     /// Returns an Output wrapping stdout.
     pub fn stdout() -> Self {
         Output::Standard(io::stdout())
     }
 
+    /// Returns an Output wrapping stderr.
+    pub fn stderr() -> Self {
+        Output::StandardError(io::stderr())
+    }
+
     /// Returns an Output wrapping a Cursor.
     pub fn memory() -> Self {
         Output::Memory(Cursor::new(vec![]))
     }
 
-    /// Returns an Output wrapping a writeable file.
-    pub fn file(path: &str) -> io::Result<Self> {
+    /// Returns an Output appending to the given vector, without copying it. See `into_vec` to get
+    /// it back.
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        let mut cursor = Cursor::new(data);
+        cursor.set_position(cursor.get_ref().len() as u64);
+        Output::Memory(cursor)
+    }
+
+    /// Returns an Output appending to a vector shared behind a mutex, along with a handle to that
+    /// vector. Clone the handle to inspect the bytes, or to build more outputs writing to it.
+    pub fn shared_vec() -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let data = Arc::new(Mutex::new(vec![]));
+        let vec = writers::WithKind::new(writers::SharedVec(data.clone()), BufferKind::Memory);
+        (Output::Adapter(Box::new(vec)), data)
+    }
+
+    /// Same as `write_all`, but a failing write returns a `BufferError::Write`, to tell it apart
+    /// from failing to open the output.
+    pub fn write_all_checked(&mut self, data: &[u8]) -> Result<(), BufferError> {
+        self.write_all(data).map_err(|source| BufferError::Write { source })
+    }
+
+    /// Returns the contents of a memory Output, without copying them. Returns `None` for other
+    /// outputs.
+    pub fn into_vec(self) -> Option<Vec<u8>> {
+        match self {
+            Output::Memory(c) => Some(c.into_inner()),
+            _ => None,
+        }
+    }
+
+    /// Returns an Output wrapping a writeable file. The file is created if it doesn't exist.
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::file_in(&RealFs, path)
+    }
+
+    /// Returns an Output wrapping a file opened from the given filesystem.
+    pub fn file_in<P: AsRef<Path>>(fs: &dyn FileSystem, path: P) -> io::Result<Self> {
+        fs.open_write(path.as_ref())
+    }
+
+    /// Same as `file`, but refusing to follow the last component of the path if it is a symbolic
+    /// link (`O_NOFOLLOW`). Opening a symlink then fails with `ELOOP`, rather than writing to
+    /// wherever it points.
+    ///
+    /// Only available on Unix: Windows has no such flag, and only follows symbolic links when
+    /// opening. Returns an `Unsupported` error on the Unix platforms and architectures the value of
+    /// `O_NOFOLLOW` isn't known for.
+    #[cfg(unix)]
+    pub fn file_no_follow<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .custom_flags(sys::o_nofollow()?)
+            .open(path.as_ref())
+            .map(Output::File)
+    }
+
+    /// Returns an Output over the file at `path`, which is only opened on the first write, the same
+    /// way `file` opens it. Opening errors are returned by that first write.
+    ///
+    /// Nothing happens to the file if nothing is ever written. Until the first write, syncing (see
+    /// `checkpoint` and `close`) does nothing, and helpers acting on the file descriptor, such as
+    /// `preallocate` and `set_nonblocking`, have no file to act on.
+    pub fn lazy_file<P: AsRef<Path>>(path: P) -> Output {
+        Output::Adapter(Box::new(writers::LazyFile::new(path.as_ref().to_path_buf())))
+    }
+
+    /// Returns an Output appending to a file, created if it doesn't exist, which is exclusively
+    /// locked for the duration of every write.
+    ///
+    /// Each write is written in full while holding the lock, so records written in a single call
+    /// (e.g. with `write_all`) never interleave with records from other threads or processes
+    /// appending to the same file the same way. The lock is advisory: other writers ignore it.
+    pub fn file_append_locked<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = fs::OpenOptions::new().append(true).create(true).open(path.as_ref())?;
+        Ok(Output::Adapter(Box::new(writers::LockedAppend::new(file))))
+    }
+
+    /// Same as `file`, but with direct IO (`O_DIRECT`), bypassing the page cache.
+    ///
+    /// Direct IO has alignment requirements, usually to the logical block size of the device (512
+    /// or 4096 bytes): the buffers written, their length and the file offsets must all be aligned,
+    /// otherwise writes fail with `EINVAL`. Use `AlignedBuf` for the buffers, and avoid wrappers
+    /// writing from buffers of their own.
+    ///
+    /// Returns an `Unsupported` error on the architectures the value of `O_DIRECT` isn't known for.
+    #[cfg(target_os = "linux")]
+    pub fn file_direct<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
         fs::OpenOptions::new()
             .write(true)
             .create(true)
-            .open(path)
+            .truncate(false)
+            .custom_flags(sys::o_direct()?)
+            .open(path.as_ref())
             .map(Output::File)
     }
 
+    /// Returns a builder for an Output over the file at `path`, to set opening options like
+    /// appending, truncation, permissions, buffering or atomic replacement in one place.
+    pub fn builder<P: AsRef<Path>>(path: P) -> OutputBuilder {
+        OutputBuilder::new(path.as_ref())
+    }
+
+    /// Returns an Output wrapping a new temporary file, which is deleted once closed.
+    ///
+    /// On Linux the file is created without a name (`O_TMPFILE`) where supported. Elsewhere it is
+    /// created with a random name in the temp directory, which is removed right away on Unix, and
+    /// on close on Windows. See `temp_with_path` to get that name where it exists.
+    pub fn temp() -> io::Result<Self> {
+        Self::temp_with_path().map(|(out, _)| out)
+    }
+
+    /// Same as `temp`, also returning the path of the temporary file. That is `None` on Unix, where
+    /// the file has no name left once opened.
+    pub fn temp_with_path() -> io::Result<(Self, Option<PathBuf>)> {
+        temp::open().map(|(file, path)| (Output::File(file), path))
+    }
+
+    /// Returns an Output wrapping a writeable file, forcing its extension to `ext` (with or without
+    /// its leading dot). Any existing extension is replaced, otherwise it is appended.
+    ///
+    /// The path actually opened is returned along with the Output.
+    pub fn file_with_extension<P: AsRef<Path>>(
+        path: P,
+        ext: &str,
+    ) -> io::Result<(Self, PathBuf)> {
+        let path = path.as_ref().with_extension(ext.trim_start_matches('.'));
+        Self::file(&path).map(|out| (out, path))
+    }
+
+    /// Returns an Output wrapping a writeable file, in binary mode.
+    ///
+    /// Bytes are written to the file exactly as given, on every platform. This is the same as
+    /// `Output::file`, and is provided to make the intent explicit next to `Output::file_text`.
+    pub fn file_binary<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::file(path)
+    }
+
+    /// Returns an Output wrapping a writeable file, in text mode.
+    ///
+    /// On Windows, every `\n` written is translated into `\r\n`, like C's text mode does. On other
+    /// platforms, text mode is the same as binary mode and bytes are written as-is.
+    pub fn file_text<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        #[cfg(windows)]
+        {
+            Ok(Output::Adapter(Box::new(newline::CrlfWriter::new(Self::file(path)?))))
+        }
+        #[cfg(not(windows))]
+        {
+            Self::file(path)
+        }
+    }
+
     /// Returns either a wrapped file buffer, or stdin, depending on the argument passed in.
     ///
     /// The function selects the buffer following these rules:
     /// - No value, or the a literal "-" returns stdin.
+    /// - On Unix, a file descriptor number, either bare ("3") or as "/dev/fd/3", returns a wrapped
+    ///   file buffer over a duplicate of that already-open descriptor, which is left open. This
+    ///   shadows a file with the same name, which can still be opened as "./3". The standard
+    ///   descriptors 0, 1 and 2, and numbers which aren't open descriptors, are opened as paths.
     /// - Any other value returns a wrapped file buffer. The file is opened with std::fs::OpenOptions,
-    ///  therefore the parent folder (or the file itself, if it already exists) is required to be
-    /// writable for the operation to succeed.
+    ///   therefore the parent folder (or the file itself, if it already exists) is required to be
+    ///   writable for the operation to succeed.
     pub fn from_arg(arg: Option<&str>) -> io::Result<Self> {
         match arg {
             None | Some("-") => Ok(Self::stdout()),
-            Some(fname) => Self::file(fname),
+            Some(fname) => {
+                #[cfg(unix)]
+                if let Some(file) = file_from_fd_arg(fname) {
+                    return file.map(Output::File);
+                }
+                Self::file(fname)
+            }
+        }
+    }
+
+    /// Same as `from_arg`, but failing to open the buffer returns a `BufferError::Open` carrying
+    /// the argument as its path.
+    pub fn from_arg_checked(arg: Option<&str>) -> Result<Self, BufferError> {
+        Self::from_arg(arg).map_err(|source| BufferError::Open {
+            path: PathBuf::from(arg.unwrap_or("-")),
+            source,
+        })
+    }
+
+    /// Same as `from_arg`, but taking a raw OS string, as given by `std::env::args_os`. Arguments
+    /// which aren't valid UTF-8 are opened as paths as-is, without any lossy conversion.
+    pub fn from_arg_os(arg: Option<&OsStr>) -> io::Result<Self> {
+        match arg.map(|arg| arg.to_str().ok_or(arg)) {
+            None => Self::from_arg(None),
+            Some(Ok(arg)) => Self::from_arg(Some(arg)),
+            Some(Err(path)) => Self::file(path),
+        }
+    }
+
+    /// Wraps this Output in a buffer coalescing small writes.
+    ///
+    /// Writes smaller than `small_threshold` bytes accumulate in a buffer of `cap` bytes, which is
+    /// written out when full. Larger writes flush the buffer, then go straight to this Output, to
+    /// avoid copying large buffers twice. Buffered bytes are written out on flush and on drop.
+    pub fn smart_buffered(self, small_threshold: usize, cap: usize) -> Output {
+        Output::Buffered(Box::new(BufferedOutput::new(self, small_threshold, cap)))
+    }
+
+    /// Wraps this Output so that every write is immediately flushed all the way down to the OS,
+    /// through any buffering this Output has.
+    ///
+    /// Useful when debugging ordering issues between several outputs, like stdout and stderr.
+    pub fn unbuffered(self) -> Output {
+        Output::Adapter(Box::new(writers::Unbuffered::new(self)))
+    }
+
+    /// Wraps this Output so that control bytes, except newlines and tabs, are written in a visible
+    /// caret notation (`^[` for ESC, `^?` for DEL), like `cat -v` does. This makes it safe to echo
+    /// untrusted input to a terminal, as escape sequences can't get through. Other bytes, including
+    /// UTF-8 text, are written as-is.
+    ///
+    /// C1 control codes are written as `M-` followed by the caret notation (`M-^[` for CSI),
+    /// whether they come as single bytes (0x80 to 0x9f) outside of any valid UTF-8 sequence, or as
+    /// UTF-8 encoded characters (U+0080 to U+009F). A UTF-8 sequence split across writes is held
+    /// back until its end is written; `finish` writes a sequence which was left incomplete as a
+    /// single U+FFFD replacement character.
+    pub fn sanitize_terminal(self) -> Output {
+        Output::Adapter(Box::new(writers::SanitizeTerminal::new(self)))
+    }
+
+    /// Wraps this Output so that lines identical to the line right before them are dropped, like
+    /// `uniq` does.
+    ///
+    /// Lines are only written once their newline is, so a last line without a newline is only
+    /// written when finishing or closing the output.
+    pub fn uniq(self) -> Output {
+        Output::Adapter(Box::new(writers::Uniq::new(self)))
+    }
+
+    /// Returns an Output writing everything to each of `outputs`, on a best-effort basis.
+    ///
+    /// An output failing doesn't stop the others: `on_error` is called with its index and the
+    /// error, and the write goes on. Writes and flushes only fail if they failed on every output.
+    /// Outputs which failed are still written to afterwards. Finishing the returned Output only
+    /// flushes the outputs, without finishing the adapters among them.
+    pub fn broadcast<F>(outputs: Vec<Output>, on_error: F) -> Output
+    where
+        F: FnMut(usize, io::Error) + Send + 'static,
+    {
+        Output::Custom(Box::new(writers::Broadcast::new(outputs, on_error)))
+    }
+
+    /// Wraps this Output so that every byte it accepts is hashed, returning the wrapped Output
+    /// along with a handle giving the digest of the bytes written so far, at any point. The digest
+    /// is also given by `current_digest`.
+    pub fn running_hash(self, algo: HashAlgorithm) -> (Output, HashHandle) {
+        let handle = HashHandle::new(algo);
+        let output = Output::Adapter(Box::new(writers::RunningHash::new(self, handle.clone())));
+        (output, handle)
+    }
+
+    /// Returns the digest of the bytes written so far through the first hashing adapter found in
+    /// this Output (see `running_hash`). Bytes still held by a buffer in front of it aren't hashed
+    /// yet. Returns `None` if this Output doesn't hash.
+    pub fn current_digest(&self) -> Option<Vec<u8>> {
+        match self {
+            Output::Adapter(a) => {
+                let any: &dyn Any = a.as_ref();
+                match any.downcast_ref::<writers::RunningHash>() {
+                    Some(hash) => Some(hash.current_digest()),
+                    None => a.inner().current_digest(),
+                }
+            }
+            Output::Buffered(b) => b.inner().current_digest(),
+            _ => None,
+        }
+    }
+
+    /// Wraps this Output so that finishing or closing it appends the CRC32C checksum of everything
+    /// written, as 4 little-endian bytes. See `Input::verify_crc_footer`.
+    pub fn with_crc_footer(self) -> Output {
+        Output::Adapter(Box::new(writers::CrcFooter::new(self)))
+    }
+
+    /// Wraps this Output so that tab-separated rows are written with their columns aligned, like
+    /// `column -t` does: each cell but the last of a row is padded with spaces to the width of the
+    /// widest cell of its column, plus two.
+    ///
+    /// All the rows have to be known to compute the widths, so they are buffered until flushing,
+    /// finishing or closing the output. Rows flushed together are aligned together, independently
+    /// of the rows written later. A last row without a newline is only written when finishing or
+    /// closing the output, terminated by a newline.
+    pub fn table(self) -> Output {
+        Output::Adapter(Box::new(writers::Table::new(self)))
+    }
+
+    /// Wraps this Output so that lines are kept within `width` columns, by breaking them at spaces
+    /// and tabs, or in the middle of words longer than a whole line. Newlines already written are
+    /// kept, and columns are counted in characters.
+    ///
+    /// Words are only written once the whitespace after them is, so a last word without any is
+    /// only written when finishing or closing the output.
+    pub fn wrapped(self, width: usize) -> Output {
+        Output::Adapter(Box::new(writers::Wrapped::new(self, width)))
+    }
+
+    /// Wraps this Output so that every line is indented with `unit` repeated `level` times. The
+    /// indentation is written along with the first byte of each line, and blank lines are left
+    /// unindented. The level can be changed afterwards with `set_indent`.
+    pub fn indented(self, level: usize, unit: &str) -> Output {
+        Output::Adapter(Box::new(writers::Indented::new(self, level, unit)))
+    }
+
+    /// Changes the indentation level of the first indenting adapter found in this Output (see
+    /// `indented`), for the lines starting after this call. Buffers and adapters in front of it are
+    /// flushed first, so that the bytes written before the call keep the previous level. Returns
+    /// an `Unsupported` error if this Output doesn't indent.
+    pub fn set_indent(&mut self, level: usize) -> io::Result<()> {
+        match self {
+            Output::Adapter(a) => {
+                let any: &mut dyn Any = a.as_mut();
+                match any.downcast_mut::<writers::Indented>() {
+                    Some(indented) => {
+                        indented.set_level(level);
+                        Ok(())
+                    }
+                    None => {
+                        a.flush()?;
+                        a.inner_mut().set_indent(level)
+                    }
+                }
+            }
+            Output::Buffered(b) => {
+                b.flush()?;
+                b.inner_mut().set_indent(level)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "output is not indented")),
+        }
+    }
+
+    /// Returns the kind of store backing this Output. Buffered outputs return the kind of the
+    /// Output they wrap, and adapters the kind given by `OutputAdapter::kind`, the same by default.
+    pub fn kind(&self) -> BufferKind {
+        match self {
+            Output::Standard(_) | Output::StandardError(_) => BufferKind::Standard,
+            Output::Memory(_) => BufferKind::Memory,
+            Output::File(_) => BufferKind::File,
+            Output::Buffered(b) => b.inner().kind(),
+            Output::Adapter(a) => a.kind(),
+            Output::Custom(_) => BufferKind::Custom,
+        }
+    }
+
+    /// Returns the wrapped stdout handle if this Output writes to stdout, to use std-specific APIs
+    /// like `lock`. Returns `None` for other outputs, wrapped ones included.
+    pub fn as_stdout(&self) -> Option<&io::Stdout> {
+        match self {
+            Output::Standard(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns whether every byte written has been written out of any buffering this Output has.
+    /// Unbuffered outputs are always flushed.
+    pub fn is_flushed(&self) -> bool {
+        match self {
+            Output::Buffered(b) => b.is_flushed(),
+            Output::Adapter(a) => a.is_flushed(),
+            _ => true,
+        }
+    }
+
+    /// Flushes the output, returning how many buffered bytes were written out in the process.
+    ///
+    /// On failure, the number of buffered bytes written out before the error is returned along with
+    /// it. Unbuffered outputs have nothing to write out, and always report 0 bytes.
+    pub fn flush_counted(&mut self) -> Result<u64, (u64, io::Error)> {
+        match self {
+            Output::Buffered(b) => b.flush_counted(),
+            _ => self.flush().map(|_| 0).map_err(|e| (0, e)),
+        }
+    }
+
+    /// Finishes the output, writing out any final bytes adapters may have (like a trailing
+    /// newline), and flushes it.
+    ///
+    /// Finishing more than once doesn't write the final bytes again.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Output::Buffered(b) => {
+                b.flush_buf().map_err(|(_, e)| e)?;
+                b.inner_mut().finish()
+            }
+            Output::Adapter(a) => {
+                a.finish()?;
+                a.inner_mut().finish()
+            }
+            _ => self.flush(),
+        }
+    }
+
+    /// Finishes and closes the output, returning any error that occurred in the process.
+    ///
+    /// Files are also synced to disk, as some filesystems only report write errors at that point.
+    /// Dropping an Output silently ignores those errors, and doesn't finish adapters.
+    pub fn close(mut self) -> io::Result<()> {
+        self.finish()?;
+        self.sync(false)
+    }
+
+    /// Marks a durability point: everything written so far is flushed, and for files, synced to
+    /// disk before returning.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.sync(true)
+    }
+
+    /// Syncs the underlying file to disk, if any. Only the file contents are synced when
+    /// `data_only` is set, otherwise its metadata is synced as well.
+    fn sync(&mut self, data_only: bool) -> io::Result<()> {
+        match self {
+            Output::File(f) if data_only => f.sync_data(),
+            Output::File(f) => f.sync_all(),
+            Output::Buffered(b) => b.inner_mut().sync(data_only),
+            Output::Adapter(a) => a.sync(data_only),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wraps this Output so that finishing or closing it appends a newline, unless the last byte
+    /// written already was one. Nothing is appended to an empty output.
+    pub fn ensure_trailing_newline(self) -> Output {
+        Output::Adapter(Box::new(writers::TrailingNewline::new(self)))
+    }
+
+    /// Wraps this Output so that everything written to it is also captured in memory, which the
+    /// returned handle gives access to.
+    ///
+    /// Only the bytes accepted by this Output are captured.
+    pub fn with_capture(self) -> (Output, CaptureHandle) {
+        let (capture, handle) = writers::Capture::new(self);
+        (Output::Adapter(Box::new(capture)), handle)
+    }
+
+    /// Empties this Output to reuse it, as if nothing had been written yet.
+    ///
+    /// Memory buffers are cleared, keeping their allocation, and files are truncated. Both are
+    /// rewound to the start. Buffered outputs drop their buffer and reset the Output they wrap.
+    /// Other outputs return an `Unsupported` error.
+    pub fn reset(&mut self) -> io::Result<()> {
+        match self {
+            Output::Memory(m) => {
+                m.get_mut().clear();
+                m.set_position(0);
+                Ok(())
+            }
+            Output::File(f) => {
+                f.set_len(0)?;
+                f.seek(SeekFrom::Start(0)).map(|_| ())
+            }
+            Output::Buffered(b) => {
+                b.discard();
+                b.inner_mut().reset()
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "output cannot be reset")),
+        }
+    }
+
+    /// Writes `payload` as a frame: its length as a big-endian `u32`, followed by its bytes. See
+    /// `Input::read_frame`.
+    ///
+    /// Payloads longer than `MAX_FRAME_LEN` are rejected with an `InvalidInput` error, as they
+    /// couldn't be read back.
+    pub fn write_frame(&mut self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > MAX_FRAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+        }
+        self.write_all(&(payload.len() as u32).to_be_bytes())?;
+        self.write_all(payload)
+    }
+
+    /// Returns whether both buffers are memory buffers with the same contents. Other buffers can't
+    /// be compared, and always return `false`.
+    pub fn content_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Output::Memory(a), Output::Memory(b)) => a.get_ref() == b.get_ref(),
+            _ => false,
+        }
+    }
+
+    /// Turns this Output into a cloneable handle, which can be written to from several threads.
+    pub fn shared(self) -> SharedOutput {
+        SharedOutput::new(self)
+    }
+
+    /// Seeks to the end of the output, returning the new position. See `Seek` for supported
+    /// outputs.
+    pub fn seek_end(&mut self) -> io::Result<u64> {
+        self.seek(SeekFrom::End(0))
+    }
+
+    /// Seeks `delta` bytes forward (or backward, if negative) from the current position, returning
+    /// the new position. See `Seek` for supported outputs.
+    pub fn seek_relative(&mut self, delta: i64) -> io::Result<u64> {
+        self.seek(SeekFrom::Current(delta))
+    }
+
+    /// Writes `buf` at the given offset, without moving the current position.
+    ///
+    /// Files use positional writes (`pwrite`), and memory buffers restore their position after
+    /// writing. Other outputs return an `Unsupported` error.
+    #[cfg(unix)]
+    pub fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        use std::os::unix::fs::FileExt;
+        match self {
+            Output::File(f) => f.write_at(buf, offset),
+            Output::Memory(m) => {
+                let pos = m.position();
+                m.set_position(offset);
+                let ret = m.write(buf);
+                m.set_position(pos);
+                ret
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "output cannot write at an offset")),
+        }
+    }
+
+    /// Makes writes to the underlying file descriptor (a file, pipe or socket) non-blocking, or
+    /// blocking again, by setting `O_NONBLOCK`. Non-blocking writes return a `WouldBlock` error
+    /// instead of waiting for room, e.g. when a pipe is full.
+    ///
+    /// The flag is shared with every other descriptor for the same open file. Memory and stdout
+    /// outputs are left as they are, and custom writers return an `Unsupported` error, as do files
+    /// on the platforms the value of `O_NONBLOCK` isn't known for.
+    #[cfg(unix)]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        match self {
+            Output::File(f) => sys::set_nonblocking(f.as_raw_fd(), nonblocking),
+            Output::Buffered(b) => b.inner().set_nonblocking(nonblocking),
+            Output::Adapter(a) => a.inner().set_nonblocking(nonblocking),
+            Output::Standard(_) | Output::StandardError(_) | Output::Memory(_) => Ok(()),
+            Output::Custom(_) => {
+                Err(io::Error::new(io::ErrorKind::Unsupported, "output has no file descriptor"))
+            }
+        }
+    }
+
+    /// Reserves disk space for the first `len` bytes of the underlying file, extending it to `len`
+    /// bytes if it is shorter, so that writing them later can't fail on a full disk.
+    ///
+    /// Space is allocated with `fallocate`, falling back to `set_len` on filesystems not
+    /// supporting it. This is a no-op for other outputs.
+    #[cfg(target_os = "linux")]
+    pub fn preallocate(&self, len: u64) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        match self {
+            Output::File(f) => {
+                if !sys::preallocate(f.as_raw_fd(), len)? && f.metadata()?.len() < len {
+                    f.set_len(len)?;
+                }
+                Ok(())
+            }
+            Output::Buffered(b) => b.inner().preallocate(len),
+            Output::Adapter(a) => a.inner().preallocate(len),
+            _ => Ok(()),
+        }
+    }
+
+    /// Turns this Output into an Input reading back what was written, from the start.
+    ///
+    /// Files are flushed and reopened read-only, and memory buffers hand their contents over
+    /// without copying. Other outputs cannot be read back, and return an `Unsupported` error, as do
+    /// files on platforms where they can't be reopened without their path.
+    pub fn reopen_for_reading(self) -> io::Result<Input> {
+        match self {
+            Output::Memory(c) => Ok(Input::Memory(Cursor::new(c.into_inner()))),
+            Output::File(mut f) => {
+                f.flush()?;
+                reopen_read(&f).map(Input::File)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "output cannot be read back")),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Write for Output {
     /// Writes data into the underlying buffer.
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             Output::Standard(ref mut s) => s.write(buf),
+            Output::StandardError(ref mut s) => s.write(buf),
             Output::Memory(ref mut m) => m.write(buf),
             Output::File(ref mut f) => f.write(buf),
+            Output::Buffered(ref mut b) => b.write(buf),
+            Output::Adapter(ref mut a) => a.write(buf),
+            Output::Custom(ref mut w) => w.write(buf),
         }
     }
 
@@ -143,12 +1486,37 @@ impl Write for Output {
     fn flush(&mut self) -> Result<(), Error> {
         match self {
             Output::Standard(ref mut s) => s.flush(),
+            Output::StandardError(ref mut s) => s.flush(),
             Output::Memory(ref mut m) => m.flush(),
             Output::File(ref mut f) => f.flush(),
+            Output::Buffered(ref mut b) => b.flush(),
+            Output::Adapter(ref mut a) => a.flush(),
+            Output::Custom(ref mut w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Seek for Output {
+    /// Seeks the underlying buffer. Buffered outputs write out their buffer first.
+    ///
+    /// Only memory and file outputs can seek; other outputs return an `Unsupported` error. As with
+    /// files, memory outputs can seek past their end, and the gap is filled with zeros on the next
+    /// write.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Output::Memory(m) => m.seek(pos),
+            Output::File(f) => f.seek(pos),
+            Output::Buffered(b) => {
+                b.flush_buf().map_err(|(_, e)| e)?;
+                b.inner_mut().seek(pos)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "output is not seekable")),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl InputOutput {
     /// Returns an InputOutput wrapping stdin and stdout.
     pub fn stdio() -> InputOutput {
@@ -161,43 +1529,290 @@ impl InputOutput {
     }
 
     /// Returns an InputOutput wrapping a readable and writable file.
-    pub fn file(path: &str) -> io::Result<InputOutput> {
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<InputOutput> {
         fs::OpenOptions::new().read(true).write(true).open(path).map(InputOutput::File)
     }
 
+    /// Returns an InputOutput wrapping a new temporary file, which is deleted once closed. See
+    /// `Output::temp` for details.
+    pub fn temp() -> io::Result<InputOutput> {
+        Self::temp_with_path().map(|(io, _)| io)
+    }
+
+    /// Same as `temp`, also returning the path of the temporary file. See
+    /// `Output::temp_with_path` for details.
+    pub fn temp_with_path() -> io::Result<(InputOutput, Option<PathBuf>)> {
+        temp::open().map(|(file, path)| (InputOutput::File(file), path))
+    }
+
     /// Returns either a wrapped file buffer, or stdin, depending on the argument passed in.
     ///
     /// The function selects the buffer following these rules:
     /// - No value, or the a literal "-" returns stdin.
+    /// - On Unix, a file descriptor number, either bare ("3") or as "/dev/fd/3", returns a wrapped
+    ///   file buffer over a duplicate of that already-open descriptor, which is left open. This
+    ///   shadows a file with the same name, which can still be opened as "./3". The standard
+    ///   descriptors 0, 1 and 2, and numbers which aren't open descriptors, are opened as paths.
     /// - Any other value returns a wrapped file buffer. The file is opened with std::fs::OpenOptions,
-    ///  therefore the file is required to exist, and be readable *and* writable for the operation
-    /// to succeed.
+    ///   therefore the file is required to exist, and be readable *and* writable for the operation
+    ///   to succeed.
     pub fn from_arg(arg: Option<&str>) -> io::Result<InputOutput> {
         match arg {
             None | Some("-") => Ok(Self::stdio()),
-            Some(path) => Self::file(path),
+            Some(path) => {
+                #[cfg(unix)]
+                if let Some(file) = file_from_fd_arg(path) {
+                    return file.map(InputOutput::File);
+                }
+                Self::file(path)
+            }
+        }
+    }
+
+    /// Same as `from_arg`, but failing to open the buffer returns a `BufferError::Open` carrying
+    /// the argument as its path.
+    pub fn from_arg_checked(arg: Option<&str>) -> Result<Self, BufferError> {
+        Self::from_arg(arg).map_err(|source| BufferError::Open {
+            path: PathBuf::from(arg.unwrap_or("-")),
+            source,
+        })
+    }
+
+    /// Same as `from_arg`, but taking a raw OS string, as given by `std::env::args_os`. Arguments
+    /// which aren't valid UTF-8 are opened as paths as-is, without any lossy conversion.
+    pub fn from_arg_os(arg: Option<&OsStr>) -> io::Result<Self> {
+        match arg.map(|arg| arg.to_str().ok_or(arg)) {
+            None => Self::from_arg(None),
+            Some(Ok(arg)) => Self::from_arg(Some(arg)),
+            Some(Err(path)) => Self::file(path),
+        }
+    }
+
+    /// Flushes and closes the buffer, returning any error that occurred in the process.
+    ///
+    /// Files are also synced to disk, as some filesystems only report write errors at that point.
+    /// Dropping an InputOutput silently ignores those errors.
+    pub fn close(mut self) -> io::Result<()> {
+        self.flush()?;
+        match self {
+            InputOutput::File(f) => f.sync_all(),
+            InputOutput::Pending(ahead) => ahead.inner.close(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether both buffers are memory buffers with the same contents. Other buffers can't
+    /// be compared, and always return `false`.
+    pub fn content_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (InputOutput::Memory(a), InputOutput::Memory(b)) => a.get_ref() == b.get_ref(),
+            _ => false,
+        }
+    }
+
+    /// Returns the kind of store backing this InputOutput. Pending bytes are ignored, and the kind
+    /// of the InputOutput they come from is returned.
+    pub fn kind(&self) -> BufferKind {
+        match self {
+            InputOutput::Standard(..) => BufferKind::Standard,
+            InputOutput::Memory(_) => BufferKind::Memory,
+            InputOutput::File(_) => BufferKind::File,
+            InputOutput::Pending(ahead) => ahead.inner.kind(),
+            InputOutput::Custom(_) => BufferKind::Custom,
+        }
+    }
+
+    /// Wraps this InputOutput so that reads, writes and flushes failing with a transient error
+    /// (`WouldBlock`, `TimedOut` or `Interrupted`) are retried following the given policy. Other
+    /// errors are returned right away.
+    pub fn with_retry(self, policy: RetryPolicy) -> InputOutput {
+        InputOutput::Custom(Box::new(duplex::Retry::new(self, policy)))
+    }
+
+    /// Wraps this InputOutput so that the bytes actually read and written are passed to `on_read`
+    /// and `on_write` respectively, after each successful read or write. Useful for logging
+    /// everything exchanged over the wire.
+    pub fn with_trace<R, W>(self, on_read: R, on_write: W) -> InputOutput
+    where
+        R: FnMut(&[u8]) + Send + 'static,
+        W: FnMut(&[u8]) + Send + 'static,
+    {
+        InputOutput::Custom(Box::new(duplex::Trace::new(self, on_read, on_write)))
+    }
+
+    /// Reads a full line (terminated by `\n`) into `buf`, without blocking on a partial line.
+    ///
+    /// Bytes are accumulated internally until a newline is available, at which point the line,
+    /// newline included, is appended to `buf` and its length returned. `None` is returned when the
+    /// underlying buffer would block before a full line is available; call again once more data
+    /// arrived. At EOF, any remaining unterminated bytes are returned as the last line, and
+    /// `Some(0)` is returned once everything has been consumed.
+    ///
+    /// A line which isn't valid UTF-8 fails with an `InvalidData` error, and is left pending: its
+    /// bytes can still be read with `read`.
+    pub fn read_line_nonblocking(&mut self, buf: &mut String) -> io::Result<Option<usize>> {
+        if !matches!(self, InputOutput::Pending(..)) {
+            let inner = mem::replace(self, InputOutput::memory());
+            *self = InputOutput::Pending(ReadAhead { pending: vec![], inner: Box::new(inner) });
+        }
+        let (pending, inner) = match self {
+            InputOutput::Pending(ReadAhead { pending, inner }) => (pending, inner),
+            _ => unreachable!(),
+        };
+        let mut chunk = [0; 1024];
+        loop {
+            if let Some(i) = pending.iter().position(|&b| b == b'\n') {
+                return take_line(pending, i + 1, buf).map(Some);
+            }
+            match inner.read(&mut chunk) {
+                Ok(0) => return take_line(pending, pending.len(), buf).map(Some),
+                Ok(n) => pending.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Parses a file descriptor argument ("3" or "/dev/fd/3"), and duplicates the descriptor if it is
+/// open. Returns `None` if the argument should be opened as a path instead: it isn't a number, it
+/// is one of the standard descriptors 0, 1 and 2, or no such descriptor is open.
+#[cfg(all(feature = "std", unix))]
+fn file_from_fd_arg(arg: &str) -> Option<io::Result<fs::File>> {
+    use std::os::unix::io::{BorrowedFd, RawFd};
+    let num = arg.strip_prefix("/dev/fd/").unwrap_or(arg);
+    if num.is_empty() || !num.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let fd: RawFd = num.parse().ok().filter(|&fd| fd > 2)?;
+    if !sys::is_open(fd) {
+        return None;
+    }
+    // SAFETY: the descriptor was just checked to be open, and is only borrowed long enough to
+    // duplicate it. The original stays owned by whoever opened it.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    Some(fd.try_clone_to_owned().map(fs::File::from))
+}
+
+/// Returns whether the suffix of a `from_arg_ext` argument looks like an encoding label, rather
+/// than part of a file name.
+#[cfg(feature = "std")]
+fn is_encoding_label(label: &str) -> bool {
+    !label.is_empty() && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Appends the bytes of `reader` to `buf` until `delim` is found, delimiter included, or the
+/// reader ends, returning the number of bytes appended. See `Input::read_until_seq`.
+#[cfg(feature = "std")]
+fn read_until_seq(reader: &mut impl BufRead, delim: &[u8], buf: &mut Vec<u8>) -> io::Result<usize> {
+    let start = buf.len();
+    loop {
+        let available = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(available) => available,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let len = available.len();
+        // Only the last bytes appended so far can hold the start of a delimiter split across reads.
+        let from = buf.len() - (buf.len() - start).min(delim.len() - 1);
+        let before = buf.len();
+        buf.extend_from_slice(available);
+        if let Some(i) = buf[from..].windows(delim.len()).position(|w| w == delim) {
+            let end = from + i + delim.len();
+            buf.truncate(end);
+            reader.consume(end - before);
+            return Ok(end - start);
+        }
+        reader.consume(len);
+    }
+    Ok(buf.len() - start)
+}
+
+/// Reads until `buf` is full or the reader ends, returning the number of bytes read.
+#[cfg(feature = "std")]
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
     }
+    Ok(filled)
 }
 
+#[cfg(feature = "std")]
+fn truncated_frame(what: &str, read: usize, expected: usize) -> io::Error {
+    let msg = format!("{}: {}/{} bytes", what, read, expected);
+    io::Error::new(io::ErrorKind::UnexpectedEof, msg)
+}
+
+/// Moves the first `len` pending bytes into `buf` as UTF-8, returning the number of bytes moved.
+/// Invalid UTF-8 is left pending.
+#[cfg(feature = "std")]
+fn take_line(pending: &mut Vec<u8>, len: usize, buf: &mut String) -> io::Result<usize> {
+    let line = std::str::from_utf8(&pending[..len])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    buf.push_str(line);
+    pending.drain(..len);
+    Ok(len)
+}
+
+#[cfg(feature = "std")]
 impl Read for InputOutput {
     /// Read from the underlying buffer.
+    ///
+    /// A read right after a write sees the bytes written. Unlike C's `FILE` streams, memory and
+    /// file buffers keep no buffer of their own, and share a single position between reads and
+    /// writes, so nothing needs flushing or seeking when switching from one to the other.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         match self {
             InputOutput::Standard(stdin, _) => stdin.read(buf),
             InputOutput::Memory(c) => c.read(buf),
-            InputOutput::File(f) => f.read(buf)
+            InputOutput::File(f) => f.read(buf),
+            InputOutput::Pending(ReadAhead { pending, inner }) => {
+                if pending.is_empty() {
+                    return inner.read(buf);
+                }
+                let n = pending.len().min(buf.len());
+                buf[..n].copy_from_slice(&pending[..n]);
+                pending.drain(..n);
+                Ok(n)
+            }
+            InputOutput::Custom(c) => c.read(buf),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Write for InputOutput {
     /// Writes into the underlying buffer.
+    ///
+    /// Memory and file buffers share a single position between reads and writes, so that a write
+    /// right after a read lands right after the bytes read. Bytes read ahead by
+    /// `read_line_nonblocking` are the only ones not yet consumed; they are discarded first,
+    /// moving the position back before them.
     fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
         match self {
             InputOutput::Standard(_, stdout) => stdout.write(buf),
             InputOutput::Memory(c) => c.write(buf),
             InputOutput::File(f) => f.write(buf),
+            InputOutput::Pending(ReadAhead { pending, inner }) => {
+                if !pending.is_empty() {
+                    // Streams which can't seek read and write independently, nothing to undo.
+                    match inner.seek(SeekFrom::Current(-(pending.len() as i64))) {
+                        Ok(_) => pending.clear(),
+                        Err(ref e) if e.kind() == io::ErrorKind::Unsupported => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                inner.write(buf)
+            }
+            InputOutput::Custom(c) => c.write(buf),
         }
     }
 
@@ -206,7 +1821,168 @@ impl Write for InputOutput {
         match self {
             InputOutput::Standard(_, stdout) => stdout.flush(),
             InputOutput::Memory(m) => m.flush(),
-            InputOutput::File(f) => f.flush()
+            InputOutput::File(f) => f.flush(),
+            InputOutput::Pending(ahead) => ahead.inner.flush(),
+            InputOutput::Custom(c) => c.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Seek for InputOutput {
+    /// Seeks the underlying buffer. Bytes pending from `read_line_nonblocking` are discarded.
+    ///
+    /// Only memory and file buffers can seek; other buffers return an `Unsupported` error.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            InputOutput::Memory(m) => m.seek(pos),
+            InputOutput::File(f) => f.seek(pos),
+            InputOutput::Pending(ReadAhead { pending, inner }) => {
+                let pos = match pos {
+                    SeekFrom::Current(delta) => SeekFrom::Current(delta - pending.len() as i64),
+                    pos => pos,
+                };
+                let ret = inner.seek(pos)?;
+                pending.clear();
+                Ok(ret)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "buffer is not seekable")),
         }
     }
 }
+
+/// Concatenates the contents of the given memory outputs, in order.
+///
+/// Returns `None` if any of the outputs isn't a memory buffer.
+#[cfg(feature = "std")]
+pub fn concat_memory(outputs: &[Output]) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    for output in outputs {
+        match output {
+            Output::Memory(c) => data.extend_from_slice(c.get_ref()),
+            _ => return None,
+        }
+    }
+    Some(data)
+}
+
+/// Creates an anonymous OS pipe, returning its read end as an Input and its write end as an
+/// Output. Both ends are file buffers.
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub fn pipe() -> io::Result<(Input, Output)> {
+    #[cfg(unix)]
+    use std::os::unix::io::OwnedFd as Owned;
+    #[cfg(windows)]
+    use std::os::windows::io::OwnedHandle as Owned;
+    let (reader, writer) = io::pipe()?;
+    let reader = fs::File::from(Owned::from(reader));
+    let writer = fs::File::from(Owned::from(writer));
+    Ok((Input::File(reader), Output::File(writer)))
+}
+
+/// Returns whether the input and output refer to the same underlying file.
+///
+/// Files are compared by device and inode on Unix, and by volume and file index on Windows. Any
+/// other kind of buffer never refers to the same file, and returns `false`.
+#[cfg(feature = "std")]
+pub fn same_file(input: &Input, output: &Output) -> io::Result<bool> {
+    match (input, output) {
+        (Input::File(a), Output::File(b)) => Ok(file_id(a)? == file_id(b)?),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+fn file_id(file: &fs::File) -> io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = file.metadata()?;
+    Ok((meta.dev(), meta.ino()))
+}
+
+#[cfg(all(feature = "std", windows))]
+fn file_id(file: &fs::File) -> io::Result<(u64, u64)> {
+    sys_windows::file_id(file)
+}
+
+#[cfg(all(feature = "std", not(any(unix, windows))))]
+fn file_id(_file: &fs::File) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "file identity is not available"))
+}
+
+/// Opens the file again, read-only and from the start, without knowing its path.
+#[cfg(all(feature = "std", unix))]
+fn reopen_read(file: &fs::File) -> io::Result<fs::File> {
+    use std::os::unix::io::AsRawFd;
+    sys::reopen_read(file.as_raw_fd())
+}
+
+#[cfg(all(feature = "std", windows))]
+fn reopen_read(file: &fs::File) -> io::Result<fs::File> {
+    sys_windows::reopen_read(file)
+}
+
+#[cfg(all(feature = "std", not(any(unix, windows))))]
+fn reopen_read(_file: &fs::File) -> io::Result<fs::File> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "files cannot be reopened"))
+}
+
+/// Copies the rest of a file input into the output, returning the number of bytes copied.
+///
+/// On Linux, when the output is a file as well, the copy happens in the kernel with
+/// `copy_file_range`, which shares the data between both files on filesystems supporting reflinks.
+/// Otherwise, or if that isn't supported, the bytes are copied with `io::copy`. Inputs other than
+/// files return an `Unsupported` error.
+#[cfg(feature = "std")]
+pub fn copy_file(src: &Input, dst: &mut Output) -> io::Result<u64> {
+    let mut src = match src {
+        Input::File(f) => f,
+        _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "input is not a file")),
+    };
+    #[cfg(target_os = "linux")]
+    if let Output::File(dst) = dst {
+        use std::os::unix::io::AsRawFd;
+        if let Some(copied) = sys::copy_file_range_all(src.as_raw_fd(), dst.as_raw_fd())? {
+            return Ok(copied);
+        }
+    }
+    io::copy(&mut src, dst)
+}
+
+/// Copies the whole input into the output, reporting progress along the way.
+///
+/// The callback receives the number of bytes copied so far, and the total length of the input
+/// when it is known (see `Input::len`). It is called every 64KB and once more at the end with the
+/// final count. Returns the total number of bytes copied.
+#[cfg(feature = "std")]
+pub fn copy_with_progress<F>(
+    input: &mut Input,
+    output: &mut Output,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    const STEP: usize = 64 * 1024;
+    let total = input.len().ok();
+    let mut buf = vec![0; STEP];
+    let mut copied = 0;
+    let mut reported = None;
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&buf[..n])?;
+        copied += n as u64;
+        if copied - reported.unwrap_or(0) >= STEP as u64 {
+            progress(copied, total);
+            reported = Some(copied);
+        }
+    }
+    if reported != Some(copied) {
+        progress(copied, total);
+    }
+    Ok(copied)
+}
@@ -0,0 +1,99 @@
+//! Memory buffers only depending on `core` and `alloc`, usable without the `std` feature.
+
+use alloc::vec::Vec;
+
+/// Minimal reading trait, implemented by the memory inputs of this module. Reading from memory
+/// can't fail, so no error is returned.
+pub trait MemRead {
+    /// Reads bytes into `buf`, returning how many were read. Returns 0 once exhausted.
+    fn read(&mut self, buf: &mut [u8]) -> usize;
+}
+
+/// Minimal writing trait, implemented by the memory outputs of this module. Writing to memory
+/// can't fail, so no error is returned.
+pub trait MemWrite {
+    /// Writes bytes from `buf`, returning how many were written.
+    fn write(&mut self, buf: &[u8]) -> usize;
+}
+
+/// Input reading from a borrowed byte slice.
+#[derive(Debug, Clone)]
+pub struct MemInput<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MemInput<'a> {
+    /// Returns an input reading `data` from the start.
+    pub fn new(data: &'a [u8]) -> Self {
+        MemInput { data, pos: 0 }
+    }
+
+    /// Returns the bytes not read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl MemRead for MemInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let rest = self.remaining();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.pos += n;
+        n
+    }
+}
+
+/// Output appending to an owned byte vector.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemOutput {
+    data: Vec<u8>,
+}
+
+impl MemOutput {
+    /// Returns an empty output.
+    pub fn new() -> Self {
+        MemOutput::default()
+    }
+
+    /// Returns the bytes written so far.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the bytes written.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl MemWrite for MemOutput {
+    fn write(&mut self, buf: &[u8]) -> usize {
+        self.data.extend_from_slice(buf);
+        buf.len()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for MemInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(MemRead::read(self, buf))
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for MemOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(MemWrite::write(self, buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
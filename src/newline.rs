@@ -0,0 +1,188 @@
+//! Newline handling helpers.
+
+use std::io::{self, BufRead, BufReader};
+#[cfg(windows)]
+use std::io::Write;
+
+use crate::Input;
+#[cfg(windows)]
+use crate::{writers::OutputAdapter, Output};
+
+/// Terminator ending a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineTerminator {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// No terminator, on the last line of an input not ending with a newline.
+    None,
+}
+
+/// Iterator over the lines of an Input, along with their terminators. See
+/// `Input::lines_with_terminators`.
+pub struct LinesWithTerminators<'a> {
+    reader: BufReader<&'a mut Input>,
+}
+
+impl<'a> LinesWithTerminators<'a> {
+    pub(crate) fn new(input: &'a mut Input) -> Self {
+        LinesWithTerminators { reader: BufReader::new(input) }
+    }
+}
+
+impl Iterator for LinesWithTerminators<'_> {
+    type Item = io::Result<(String, LineTerminator)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = vec![];
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(split_terminator(line)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Iterator over the lines of an Input, split on the terminator found first. See
+/// `Input::lines_auto`.
+pub struct LinesAuto<'a> {
+    reader: BufReader<&'a mut Input>,
+    terminator: Option<&'static [u8]>,
+}
+
+impl<'a> LinesAuto<'a> {
+    pub(crate) fn new(input: &'a mut Input) -> Self {
+        LinesAuto { reader: BufReader::new(input), terminator: None }
+    }
+
+    /// Reads up to and including the first `\n` or `\r` (along with a `\n` following it), and
+    /// records which terminator it was.
+    fn detect(&mut self, line: &mut Vec<u8>) -> io::Result<()> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            match buf.iter().position(|&b| b == b'\n' || b == b'\r') {
+                Some(i) => {
+                    let cr = buf[i] == b'\r';
+                    line.extend_from_slice(&buf[..=i]);
+                    self.reader.consume(i + 1);
+                    if !cr {
+                        self.terminator = Some(b"\n");
+                    } else if self.reader.fill_buf()?.first() == Some(&b'\n') {
+                        self.reader.consume(1);
+                        line.push(b'\n');
+                        self.terminator = Some(b"\r\n");
+                    } else {
+                        self.terminator = Some(b"\r");
+                    }
+                    return Ok(());
+                }
+                None => {
+                    let n = buf.len();
+                    line.extend_from_slice(buf);
+                    self.reader.consume(n);
+                }
+            }
+        }
+    }
+
+    /// Reads up to and including the next occurrence of the terminator.
+    fn read_line(&mut self, terminator: &[u8], line: &mut Vec<u8>) -> io::Result<()> {
+        let last = terminator[terminator.len() - 1];
+        while !line.ends_with(terminator) {
+            if self.reader.read_until(last, line)? == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for LinesAuto<'_> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = vec![];
+        let res = match self.terminator {
+            Some(terminator) => self.read_line(terminator, &mut line),
+            None => self.detect(&mut line),
+        };
+        if let Err(e) = res {
+            return Some(Err(e));
+        }
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(terminator) = self.terminator {
+            if line.ends_with(terminator) {
+                line.truncate(line.len() - terminator.len());
+            }
+        }
+        Some(String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+}
+
+/// Strips the terminator off the line, returning both.
+fn split_terminator(mut line: Vec<u8>) -> io::Result<(String, LineTerminator)> {
+    let terminator = if line.ends_with(b"\r\n") {
+        LineTerminator::CrLf
+    } else if line.ends_with(b"\n") {
+        LineTerminator::Lf
+    } else {
+        LineTerminator::None
+    };
+    line.truncate(match terminator {
+        LineTerminator::Lf => line.len() - 1,
+        LineTerminator::CrLf => line.len() - 2,
+        LineTerminator::None => line.len(),
+    });
+    String::from_utf8(line)
+        .map(|line| (line, terminator))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writer translating `\n` into `\r\n` on its way to the inner Output, mimicking C's text mode.
+#[cfg(windows)]
+pub(crate) struct CrlfWriter {
+    inner: Output,
+}
+
+#[cfg(windows)]
+impl CrlfWriter {
+    pub(crate) fn new(inner: Output) -> Self {
+        CrlfWriter { inner }
+    }
+}
+
+#[cfg(windows)]
+impl Write for CrlfWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match buf.iter().position(|&b| b == b'\n') {
+            Some(0) => self.inner.write_all(b"\r\n").map(|_| 1),
+            Some(i) => self.inner.write(&buf[..i]),
+            None => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(windows)]
+impl OutputAdapter for CrlfWriter {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
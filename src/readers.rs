@@ -0,0 +1,489 @@
+//! Reader adapters backing the `Input` wrapping methods.
+
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::crc32c::Crc32c;
+use crate::{Cancelled, HashHandle, Input};
+
+/// Reader transforming the input one line at a time, the newline included.
+///
+/// A line is only transformed once it is complete, or ends the input. If reading it fails halfway,
+/// the bytes read so far are kept, and the line is completed by the next reads.
+pub(crate) struct MapLines<F> {
+    inner: BufReader<Input>,
+    line: Vec<u8>,
+    pos: usize,
+    complete: bool,
+    f: F,
+}
+
+impl<F: FnMut(&mut Vec<u8>)> MapLines<F> {
+    pub(crate) fn new(inner: Input, f: F) -> Self {
+        MapLines { inner: BufReader::new(inner), line: vec![], pos: 0, complete: false, f }
+    }
+}
+
+impl<F: FnMut(&mut Vec<u8>)> Read for MapLines<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.complete || self.pos == self.line.len() {
+            if self.complete {
+                self.line.clear();
+                self.pos = 0;
+                self.complete = false;
+            }
+            let n = self.inner.read_until(b'\n', &mut self.line)?;
+            if n == 0 && self.line.is_empty() {
+                return Ok(0);
+            }
+            if n == 0 || self.line.ends_with(b"\n") {
+                (self.f)(&mut self.line);
+                self.complete = true;
+            }
+        }
+        let n = (self.line.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Reader transforming every byte read. See `Input::map_bytes`.
+pub(crate) struct MapBytes<F> {
+    inner: Input,
+    f: F,
+}
+
+impl<F: FnMut(u8) -> u8> MapBytes<F> {
+    pub(crate) fn new(inner: Input, f: F) -> Self {
+        MapBytes { inner, f }
+    }
+}
+
+impl<F: FnMut(u8) -> u8> Read for MapBytes<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        buf[..n].iter_mut().for_each(|b| *b = (self.f)(*b));
+        Ok(n)
+    }
+}
+
+/// Removes the spaces and tabs found right before the line terminator, or at the end of the line if
+/// it has none.
+pub(crate) fn trim_line_end(line: &mut Vec<u8>) {
+    let terminator = if line.ends_with(b"\r\n") {
+        2
+    } else if line.ends_with(b"\n") {
+        1
+    } else {
+        0
+    };
+    let content = line.len() - terminator;
+    let trimmed = line[..content]
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(0, |i| i + 1);
+    line.drain(trimmed..content);
+}
+
+/// Turns a `\r\n` line terminator into `\n`.
+pub(crate) fn crlf_to_lf(line: &mut Vec<u8>) {
+    if line.ends_with(b"\r\n") {
+        line.remove(line.len() - 2);
+    }
+}
+
+/// Reader failing with `Cancelled` once its token is set. See `Input::with_cancel`.
+pub(crate) struct Cancellable {
+    inner: Input,
+    token: Arc<AtomicBool>,
+}
+
+impl Cancellable {
+    pub(crate) fn new(inner: Input, token: Arc<AtomicBool>) -> Self {
+        Cancellable { inner, token }
+    }
+}
+
+impl Read for Cancellable {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.token.load(Ordering::SeqCst) {
+            return Err(io::Error::other(Cancelled));
+        }
+        self.inner.read(buf)
+    }
+}
+
+/// Reader failing with `TimedOut` once its deadline has passed. See `Input::with_deadline`.
+pub(crate) struct Deadline {
+    inner: Input,
+    deadline: Instant,
+}
+
+impl Deadline {
+    pub(crate) fn new(inner: Input, deadline: Instant) -> Self {
+        Deadline { inner, deadline }
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if Instant::now() > self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "read deadline exceeded"));
+        }
+        self.inner.read(buf)
+    }
+}
+
+/// Reader failing with `TimedOut` when its throughput drops below a minimum rate. See
+/// `Input::min_rate`.
+pub(crate) struct MinRate {
+    inner: Input,
+    bytes_per_sec: u64,
+    window: Duration,
+    start: Instant,
+    /// Reads done within the last window, with the time they completed.
+    reads: VecDeque<(Instant, u64)>,
+    too_slow: bool,
+}
+
+impl MinRate {
+    pub(crate) fn new(inner: Input, bytes_per_sec: u64, window: Duration) -> Self {
+        let start = Instant::now();
+        MinRate { inner, bytes_per_sec, window, start, reads: VecDeque::new(), too_slow: false }
+    }
+}
+
+impl Read for MinRate {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.too_slow {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "read rate too low"));
+        }
+        let n = self.inner.read(buf)?;
+        let now = Instant::now();
+        self.reads.push_back((now, n as u64));
+        while let Some(&(at, _)) = self.reads.front() {
+            if now.duration_since(at) <= self.window {
+                break;
+            }
+            self.reads.pop_front();
+        }
+        if n > 0 && now.duration_since(self.start) >= self.window {
+            let received: u64 = self.reads.iter().map(|&(_, n)| n).sum();
+            // The bytes were read already: hand them out, and fail from the next read on.
+            self.too_slow =
+                (received as f64) < self.bytes_per_sec as f64 * self.window.as_secs_f64();
+        }
+        Ok(n)
+    }
+}
+
+/// Reader reading at most a given number of bytes per call. See `Input::max_read_size`.
+pub(crate) struct MaxReadSize {
+    inner: Input,
+    max: usize,
+}
+
+impl MaxReadSize {
+    pub(crate) fn new(inner: Input, max: usize) -> Self {
+        assert!(max != 0, "maximum read size must be non-zero");
+        MaxReadSize { inner, max }
+    }
+}
+
+impl Read for MaxReadSize {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.max);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+/// Reader taking turns between several inputs. See `Input::round_robin`.
+pub(crate) struct RoundRobin {
+    inputs: Vec<Input>,
+    next: usize,
+}
+
+impl RoundRobin {
+    pub(crate) fn new(inputs: Vec<Input>) -> Self {
+        RoundRobin { inputs, next: 0 }
+    }
+}
+
+impl Read for RoundRobin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while !self.inputs.is_empty() {
+            let i = self.next % self.inputs.len();
+            match self.inputs[i].read(buf)? {
+                0 => {
+                    // Exhausted: the next input takes its index.
+                    self.inputs.remove(i);
+                    self.next = i;
+                }
+                n => {
+                    self.next = i + 1;
+                    return Ok(n);
+                }
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Iterator over fixed-size chunks of an Input. See `Input::chunks`.
+pub struct Chunks {
+    inner: Input,
+    size: usize,
+    /// Chunk being filled, kept across calls when a read fails partway through.
+    chunk: Vec<u8>,
+    done: bool,
+}
+
+impl Chunks {
+    pub(crate) fn new(inner: Input, size: usize) -> Self {
+        assert!(size != 0, "chunk size must be non-zero");
+        Chunks { inner, size, chunk: vec![], done: false }
+    }
+}
+
+impl Iterator for Chunks {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut len = self.chunk.len();
+        self.chunk.resize(self.size, 0);
+        while len < self.size {
+            match self.inner.read(&mut self.chunk[len..]) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.chunk.truncate(len);
+                    return Some(Err(e));
+                }
+            }
+        }
+        if len == 0 {
+            return None;
+        }
+        self.chunk.truncate(len);
+        Some(Ok(std::mem::take(&mut self.chunk)))
+    }
+}
+
+/// What a memory input does once its contents are exhausted. See `Input::memory_from_with_eof`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofBehavior {
+    /// Reads return `Ok(0)`, as usual.
+    #[default]
+    Eof,
+    /// Reads fail with `WouldBlock`, as a stalled non-blocking stream would.
+    WouldBlock,
+    /// Reads fail with an error of the given kind.
+    Error(io::ErrorKind),
+}
+
+/// Memory reader applying an `EofBehavior` once exhausted.
+pub(crate) struct MemoryWithEof {
+    inner: Cursor<Vec<u8>>,
+    eof: EofBehavior,
+}
+
+impl MemoryWithEof {
+    pub(crate) fn new(data: Vec<u8>, eof: EofBehavior) -> Self {
+        MemoryWithEof { inner: Cursor::new(data), eof }
+    }
+}
+
+impl Read for MemoryWithEof {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf)? {
+            0 if !buf.is_empty() => match self.eof {
+                EofBehavior::Eof => Ok(0),
+                EofBehavior::WouldBlock => Err(io::ErrorKind::WouldBlock.into()),
+                EofBehavior::Error(kind) => Err(kind.into()),
+            },
+            n => Ok(n),
+        }
+    }
+}
+
+/// Reader checking the CRC32C footer ending the input. See `Input::verify_crc_footer`.
+pub(crate) struct CrcVerify {
+    inner: Input,
+    crc: Crc32c,
+    /// Bytes read but not returned yet, the last 4 of which may be the footer.
+    held: Vec<u8>,
+    eof: bool,
+}
+
+impl CrcVerify {
+    pub(crate) fn new(inner: Input) -> Self {
+        CrcVerify { inner, crc: Crc32c::new(), held: vec![], eof: false }
+    }
+
+    fn verify(&self) -> io::Result<()> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let footer: [u8; 4] = self
+            .held
+            .as_slice()
+            .try_into()
+            .map_err(|_| invalid("missing checksum footer"))?;
+        if u32::from_le_bytes(footer) != self.crc.value() {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(())
+    }
+}
+
+impl Read for CrcVerify {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.held.len() <= 4 && !self.eof {
+            let start = self.held.len();
+            self.held.resize(start + buf.len().max(4096), 0);
+            match self.inner.read(&mut self.held[start..]) {
+                Ok(n) => {
+                    self.held.truncate(start + n);
+                    self.eof = n == 0;
+                }
+                Err(e) => {
+                    self.held.truncate(start);
+                    return Err(e);
+                }
+            }
+        }
+        if self.held.len() <= 4 {
+            self.verify()?;
+            return Ok(0);
+        }
+        let n = (self.held.len() - 4).min(buf.len());
+        buf[..n].copy_from_slice(&self.held[..n]);
+        self.crc.update(&buf[..n]);
+        self.held.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Reader hashing every byte read. See `Input::running_hash`.
+pub struct HashingInput {
+    inner: Input,
+    handle: HashHandle,
+}
+
+impl HashingInput {
+    pub(crate) fn new(inner: Input, handle: HashHandle) -> Self {
+        HashingInput { inner, handle }
+    }
+
+    /// Returns the digest of the bytes read so far. Hashing goes on afterwards.
+    pub fn current_digest(&self) -> Vec<u8> {
+        self.handle.current_digest()
+    }
+}
+
+impl Read for HashingInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.handle.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reader failing once a line runs longer than a limit. See `Input::max_line_len`.
+pub(crate) struct MaxLineLen {
+    inner: Input,
+    limit: usize,
+    /// Length of the line read so far, since its last newline.
+    line_len: usize,
+    too_long: bool,
+}
+
+impl MaxLineLen {
+    pub(crate) fn new(inner: Input, limit: usize) -> Self {
+        MaxLineLen { inner, limit, line_len: 0, too_long: false }
+    }
+}
+
+impl Read for MaxLineLen {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.too_long {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+        }
+        let n = self.inner.read(buf)?;
+        for (i, &b) in buf[..n].iter().enumerate() {
+            if b == b'\n' {
+                self.line_len = 0;
+            } else if self.line_len == self.limit {
+                // Hand out the bytes up to the limit, the next read fails.
+                self.too_long = true;
+                return if i == 0 { self.read(buf) } else { Ok(i) };
+            } else {
+                self.line_len += 1;
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Decoding reader which stays failed after its first error, so that a corrupted stream is never
+/// silently resumed. See `Input::take_error`.
+///
+/// `Interrupted` and `WouldBlock` errors are passed through without failing the reader.
+pub struct StickyInput {
+    inner: Box<dyn Read + Send>,
+    failed: Option<(io::ErrorKind, String)>,
+    error: Option<io::Error>,
+}
+
+impl StickyInput {
+    pub(crate) fn new<R: Read + Send + 'static>(inner: R) -> Self {
+        StickyInput { inner: Box::new(inner), failed: None, error: None }
+    }
+
+    /// Returns whether a read has failed.
+    pub fn is_failed(&self) -> bool {
+        self.failed.is_some()
+    }
+
+    /// Returns the error which failed the reader, if it hasn't been taken yet. The reader stays
+    /// failed: later reads keep returning an error of the same kind and message.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl Read for StickyInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some((kind, msg)) = &self.failed {
+            return Err(io::Error::new(*kind, msg.clone()));
+        }
+        match self.inner.read(buf) {
+            Err(e) if matches!(e.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock) => {
+                Err(e)
+            }
+            Err(e) => {
+                let err = io::Error::new(e.kind(), e.to_string());
+                self.failed = Some((e.kind(), e.to_string()));
+                self.error = Some(e);
+                Err(err)
+            }
+            res => res,
+        }
+    }
+}
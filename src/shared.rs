@@ -0,0 +1,46 @@
+//! Output shareable across threads.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::Output;
+
+/// Cloneable handle to an Output shared between threads. See `Output::shared`.
+///
+/// Each `write`, `write_all` and `write_fmt` call holds the lock for its whole duration, so that
+/// writes from different handles never interleave within a single call.
+#[derive(Clone)]
+pub struct SharedOutput {
+    inner: Arc<Mutex<Output>>,
+}
+
+impl SharedOutput {
+    pub(crate) fn new(output: Output) -> Self {
+        SharedOutput { inner: Arc::new(Mutex::new(output)) }
+    }
+
+    /// Locks the shared Output. A writer panicking while holding the lock doesn't prevent others
+    /// from writing.
+    fn lock(&self) -> MutexGuard<'_, Output> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.lock().write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        self.lock().write_fmt(args)
+    }
+}
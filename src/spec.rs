@@ -0,0 +1,60 @@
+//! Buffer descriptors, separate from live buffer handles.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::{Input, Output};
+
+/// Lightweight, comparable description of a buffer, which can be stored and opened later.
+///
+/// Specs are ordered stdin, stdout, stderr, then files by path, then memory, so that lists of
+/// buffers can be sorted deterministically.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BufferSpec {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(PathBuf),
+    Memory,
+}
+
+impl BufferSpec {
+    /// Opens the described buffer as an Input.
+    ///
+    /// Stdout and stderr cannot be read from, and return an `InvalidInput` error.
+    pub fn open_input(&self) -> io::Result<Input> {
+        match self {
+            BufferSpec::Stdin => Ok(Input::stdin()),
+            BufferSpec::Stdout | BufferSpec::Stderr => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot open an output stream as input",
+            )),
+            BufferSpec::File(path) => Input::file(path),
+            BufferSpec::Memory => Ok(Input::memory()),
+        }
+    }
+
+    /// Opens the described buffer as an Output.
+    ///
+    /// Like `Output::from_arg`, the standard stream parsed from "-" opens stdout, therefore
+    /// `BufferSpec::Stdin` opens stdout here.
+    pub fn open_output(&self) -> io::Result<Output> {
+        match self {
+            BufferSpec::Stdin | BufferSpec::Stdout => Ok(Output::stdout()),
+            BufferSpec::Stderr => Ok(Output::stderr()),
+            BufferSpec::File(path) => Output::file(path),
+            BufferSpec::Memory => Ok(Output::memory()),
+        }
+    }
+}
+
+impl From<&str> for BufferSpec {
+    /// Parses a commandline argument, following the same rules as `from_arg`: a literal "-" is
+    /// the standard stream (parsed as `BufferSpec::Stdin`), and any other value is a file path.
+    fn from(arg: &str) -> Self {
+        match arg {
+            "-" => BufferSpec::Stdin,
+            path => BufferSpec::File(PathBuf::from(path)),
+        }
+    }
+}
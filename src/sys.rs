@@ -0,0 +1,290 @@
+//! Raw Unix bindings, for the few system calls std doesn't expose.
+
+#[cfg(target_os = "linux")]
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_long;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_uint;
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::io::RawFd;
+
+extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+    fn tcsetattr(fd: c_int, action: c_int, termios: *const Termios) -> c_int;
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn posix_fadvise(fd: c_int, offset: c_long, len: c_long, advice: c_int) -> c_int;
+    fn fallocate(fd: c_int, mode: c_int, offset: c_long, len: c_long) -> c_int;
+    fn copy_file_range(
+        fd_in: c_int,
+        off_in: *mut i64,
+        fd_out: c_int,
+        off_out: *mut i64,
+        len: usize,
+        flags: c_uint,
+    ) -> isize;
+}
+
+const F_GETFD: c_int = 1;
+const TCSANOW: c_int = 0;
+const F_GETFL: c_int = 3;
+const F_SETFL: c_int = 4;
+#[cfg(target_os = "linux")]
+const EOPNOTSUPP: i32 = 95;
+
+/// Whether this is Linux on an architecture using the generic values of the flags below.
+const LINUX_GENERIC: bool = cfg!(all(
+    any(target_os = "linux", target_os = "android"),
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "riscv32",
+        target_arch = "riscv64"
+    )
+));
+/// Whether this is Linux on an Arm architecture, which has values of its own for a few flags.
+const LINUX_ARM: bool = cfg!(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "arm", target_arch = "aarch64")
+));
+/// Whether this is macOS or one of the BSDs, which share the values of the flags below.
+const BSD: bool = cfg!(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+));
+
+/// Value of `O_NOFOLLOW`, which depends on the platform, and on the architecture on Linux. `None`
+/// on the platforms it hasn't been checked for.
+const O_NOFOLLOW: Option<c_int> = if LINUX_GENERIC {
+    Some(0o400000)
+} else if LINUX_ARM {
+    Some(0o100000)
+} else if BSD {
+    Some(0x100)
+} else {
+    None
+};
+
+/// Returns the value of a flag or request, or an `Unsupported` error where its value isn't known.
+fn flag<T>(value: Option<T>, name: &str) -> io::Result<T> {
+    value.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, format!("{} is not supported here", name))
+    })
+}
+
+/// Returns the value of `O_NOFOLLOW`.
+pub(crate) fn o_nofollow() -> io::Result<c_int> {
+    flag(O_NOFOLLOW, "O_NOFOLLOW")
+}
+
+/// Value of `O_DIRECT`, which depends on the architecture. `None` on the architectures it hasn't
+/// been checked for.
+#[cfg(target_os = "linux")]
+const O_DIRECT: Option<c_int> = if LINUX_GENERIC {
+    Some(0o40000)
+} else if LINUX_ARM {
+    Some(0o200000)
+} else {
+    None
+};
+
+/// Returns the value of `O_DIRECT`.
+#[cfg(target_os = "linux")]
+pub(crate) fn o_direct() -> io::Result<c_int> {
+    flag(O_DIRECT, "O_DIRECT")
+}
+
+/// Value of `O_NONBLOCK`. `None` on the platforms and architectures it hasn't been checked for.
+const O_NONBLOCK: Option<c_int> = if LINUX_GENERIC || LINUX_ARM {
+    Some(0o4000)
+} else if BSD {
+    Some(0x4)
+} else {
+    None
+};
+
+/// Value of `FIONREAD`. `None` on the platforms and architectures it hasn't been checked for.
+const FIONREAD: Option<c_ulong> = if LINUX_GENERIC || LINUX_ARM {
+    Some(0x541B)
+} else if BSD {
+    Some(0x4004_667F)
+} else {
+    None
+};
+
+/// Value of `O_TMPFILE`, which includes `O_DIRECTORY`, whose value depends on the architecture.
+/// `None` on the architectures it hasn't been checked for.
+#[cfg(target_os = "linux")]
+const O_TMPFILE: Option<c_int> = if LINUX_GENERIC {
+    Some(0o20200000)
+} else if LINUX_ARM {
+    Some(0o20040000)
+} else {
+    None
+};
+
+/// Returns the value of `O_TMPFILE`.
+#[cfg(target_os = "linux")]
+pub(crate) fn o_tmpfile() -> io::Result<c_int> {
+    flag(O_TMPFILE, "O_TMPFILE")
+}
+
+/// Turns the -1 returned by failing system calls into the corresponding OS error.
+fn check(ret: c_int) -> io::Result<c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Returns the number of bytes that can be read from the file descriptor without blocking.
+pub(crate) fn readable_bytes(fd: RawFd) -> io::Result<usize> {
+    let fionread = flag(FIONREAD, "FIONREAD")?;
+    let mut n: c_int = 0;
+    // SAFETY: FIONREAD writes a single int through the pointer, which points to a live local.
+    check(unsafe { ioctl(fd, fionread, &mut n as *mut c_int) })?;
+    Ok(n as usize)
+}
+
+/// Returns whether the file descriptor is open.
+pub(crate) fn is_open(fd: RawFd) -> bool {
+    // SAFETY: F_GETFD takes no argument, and only queries the descriptor flags.
+    unsafe { fcntl(fd, F_GETFD) != -1 }
+}
+
+/// Sets or clears `O_NONBLOCK` on the file descriptor.
+pub(crate) fn set_nonblocking(fd: RawFd, nonblocking: bool) -> io::Result<()> {
+    let o_nonblock = flag(O_NONBLOCK, "O_NONBLOCK")?;
+    // SAFETY: F_GETFL and F_SETFL only read and set the file status flags.
+    let flags = check(unsafe { fcntl(fd, F_GETFL) })?;
+    let flags = if nonblocking { flags | o_nonblock } else { flags & !o_nonblock };
+    check(unsafe { fcntl(fd, F_SETFL, flags) }).map(|_| ())
+}
+
+/// Opens the file behind the descriptor again, read-only and from the start, through its entry in
+/// `/proc`, which still works once the file has been renamed or deleted.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn reopen_read(fd: RawFd) -> io::Result<fs::File> {
+    fs::File::open(format!("/proc/self/fd/{}", fd))
+}
+
+/// Opens the file behind the descriptor again, read-only and from the start, through the path
+/// `F_GETPATH` returns for it.
+#[cfg(target_os = "macos")]
+pub(crate) fn reopen_read(fd: RawFd) -> io::Result<fs::File> {
+    use std::ffi::{CStr, OsStr};
+    use std::os::raw::c_char;
+    use std::os::unix::ffi::OsStrExt;
+    const F_GETPATH: c_int = 50;
+    const MAXPATHLEN: usize = 1024;
+    let mut path = [0 as c_char; MAXPATHLEN];
+    // SAFETY: F_GETPATH writes a nul-terminated path of at most MAXPATHLEN bytes into the buffer.
+    check(unsafe { fcntl(fd, F_GETPATH, path.as_mut_ptr()) })?;
+    // SAFETY: the buffer was nul-terminated by the call above.
+    let path = unsafe { CStr::from_ptr(path.as_ptr()) };
+    fs::File::open(OsStr::from_bytes(path.to_bytes()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub(crate) fn reopen_read(_fd: RawFd) -> io::Result<fs::File> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "files cannot be reopened"))
+}
+
+/// Terminal settings, kept opaque: the layout of `struct termios` varies across platforms, but
+/// it always fits in here, and saving then restoring it doesn't need to look inside.
+#[derive(Clone)]
+#[repr(C, align(8))]
+pub(crate) struct Termios([u8; 256]);
+
+/// Returns the current settings of the terminal.
+pub(crate) fn get_termios(fd: RawFd) -> io::Result<Termios> {
+    let mut termios = Termios([0; 256]);
+    // SAFETY: the buffer is large and aligned enough for any `struct termios`.
+    check(unsafe { tcgetattr(fd, &mut termios) })?;
+    Ok(termios)
+}
+
+/// Applies settings previously returned by `get_termios` to the terminal, right away.
+pub(crate) fn set_termios(fd: RawFd, termios: &Termios) -> io::Result<()> {
+    // SAFETY: the settings were filled in by tcgetattr.
+    check(unsafe { tcsetattr(fd, TCSANOW, termios) }).map(|_| ())
+}
+
+/// Access pattern advice given to the kernel about a file.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Advice {
+    Sequential = 2,
+    WillNeed = 3,
+}
+
+/// Advises the kernel about how the whole file is going to be accessed.
+#[cfg(target_os = "linux")]
+pub(crate) fn fadvise(fd: RawFd, advice: Advice) -> io::Result<()> {
+    // SAFETY: posix_fadvise only takes plain values, and returns an error number on failure.
+    match unsafe { posix_fadvise(fd, 0, 0, advice as c_int) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+/// Allocates disk space for the first `len` bytes of the file, extending it if shorter. Returns
+/// `Ok(false)` if the filesystem doesn't support allocating space up front.
+#[cfg(target_os = "linux")]
+pub(crate) fn preallocate(fd: RawFd, len: u64) -> io::Result<bool> {
+    let len = c_long::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "length too large"))?;
+    // SAFETY: fallocate only takes plain values.
+    match check(unsafe { fallocate(fd, 0, 0, len) }) {
+        Ok(_) => Ok(true),
+        Err(e) if e.raw_os_error() == Some(EOPNOTSUPP) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copies the rest of `src` into `dst` in the kernel, from and to their current positions, which
+/// are moved past the bytes copied. Returns `None` if nothing could be copied because the kernel
+/// or the filesystems don't support it, or because `dst` was opened for appending, which the
+/// kernel rejects with `EBADF`.
+#[cfg(target_os = "linux")]
+pub(crate) fn copy_file_range_all(src: RawFd, dst: RawFd) -> io::Result<Option<u64>> {
+    const EBADF: i32 = 9;
+    const EXDEV: i32 = 18;
+    const EINVAL: i32 = 22;
+    const ENOSYS: i32 = 38;
+    let mut copied = 0;
+    loop {
+        // SAFETY: null offsets make the kernel use and update the file positions.
+        let n = unsafe {
+            copy_file_range(src, std::ptr::null_mut(), dst, std::ptr::null_mut(), 1 << 30, 0)
+        };
+        match n {
+            0 => return Ok(Some(copied)),
+            n if n > 0 => copied += n as u64,
+            _ => {
+                let e = io::Error::last_os_error();
+                match e.raw_os_error() {
+                    Some(EBADF) | Some(EXDEV) | Some(EINVAL) | Some(ENOSYS) | Some(EOPNOTSUPP)
+                        if copied == 0 =>
+                    {
+                        return Ok(None)
+                    }
+                    _ if e.kind() == io::ErrorKind::Interrupted => {}
+                    _ => return Err(e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Raw Windows bindings, for the few system calls std doesn't expose.
+
+use std::fs;
+use std::io;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+
+#[repr(C)]
+struct FileTime {
+    low: u32,
+    high: u32,
+}
+
+#[repr(C)]
+struct ByHandleFileInformation {
+    file_attributes: u32,
+    creation_time: FileTime,
+    last_access_time: FileTime,
+    last_write_time: FileTime,
+    volume_serial_number: u32,
+    file_size_high: u32,
+    file_size_low: u32,
+    number_of_links: u32,
+    file_index_high: u32,
+    file_index_low: u32,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetFileInformationByHandle(file: RawHandle, info: *mut ByHandleFileInformation) -> i32;
+}
+
+/// Returns the volume serial number and file index identifying the file.
+pub(crate) fn file_id(file: &fs::File) -> io::Result<(u64, u64)> {
+    let mut info = std::mem::MaybeUninit::<ByHandleFileInformation>::uninit();
+    // SAFETY: the handle is valid for the lifetime of `file`, and the call fills `info` on success.
+    let info = unsafe {
+        if GetFileInformationByHandle(file.as_raw_handle(), info.as_mut_ptr()) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        info.assume_init()
+    };
+    let index = (u64::from(info.file_index_high) << 32) | u64::from(info.file_index_low);
+    Ok((u64::from(info.volume_serial_number), index))
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn ReOpenFile(file: RawHandle, access: u32, share_mode: u32, flags: u32) -> RawHandle;
+}
+
+/// Opens the file again, read-only and from the start.
+pub(crate) fn reopen_read(file: &fs::File) -> io::Result<fs::File> {
+    const GENERIC_READ: u32 = 0x8000_0000;
+    const SHARE_ALL: u32 = 0x7;
+    const INVALID_HANDLE_VALUE: RawHandle = -1isize as RawHandle;
+    // SAFETY: the handle is valid for the lifetime of `file`, and a new handle is returned on
+    // success, which the returned File then owns.
+    unsafe {
+        let handle = ReOpenFile(file.as_raw_handle(), GENERIC_READ, SHARE_ALL, 0);
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fs::File::from_raw_handle(handle))
+    }
+}
@@ -0,0 +1,96 @@
+//! Anonymous temporary files.
+
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::PathBuf;
+use std::process;
+
+/// Opens a new readable and writable temporary file, deleted once closed. Its path is returned
+/// along with it, if it still has one once opened.
+pub(crate) fn open() -> io::Result<(fs::File, Option<PathBuf>)> {
+    #[cfg(target_os = "linux")]
+    {
+        match open_unnamed() {
+            Err(e) if is_tmpfile_unsupported(&e) => {}
+            ret => return ret.map(|file| (file, None)),
+        }
+    }
+    loop {
+        let path = random_path();
+        match open_named(&path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+            // The name is only kept where the file isn't unlinked right away.
+            Ok(file) => return Ok((file, if cfg!(unix) { None } else { Some(path) })),
+        }
+    }
+}
+
+/// Opens a file without any name in the temp directory, so that nothing is left behind.
+#[cfg(target_os = "linux")]
+fn open_unnamed() -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .mode(0o600)
+        .custom_flags(crate::sys::o_tmpfile()?)
+        .open(std::env::temp_dir())
+}
+
+/// Returns whether opening with `O_TMPFILE` failed because the kernel or filesystem doesn't
+/// support it, rather than for an actual error.
+#[cfg(target_os = "linux")]
+fn is_tmpfile_unsupported(e: &io::Error) -> bool {
+    const EISDIR: i32 = 21;
+    const EINVAL: i32 = 22;
+    const EOPNOTSUPP: i32 = 95;
+    e.kind() == io::ErrorKind::Unsupported
+        || matches!(e.raw_os_error(), Some(EISDIR) | Some(EINVAL) | Some(EOPNOTSUPP))
+}
+
+/// Returns a new random hidden file name.
+pub(crate) fn random_name() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(process::id());
+    format!(".wbuf-{:016x}", hasher.finish())
+}
+
+fn random_path() -> PathBuf {
+    std::env::temp_dir().join(random_name())
+}
+
+/// Creates the file at `path`, and removes its name right away on Unix, where the file lives on
+/// until closed.
+#[cfg(unix)]
+fn open_named(path: &PathBuf) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    fs::remove_file(path)?;
+    Ok(file)
+}
+
+/// Creates the file at `path`, asking Windows to delete it once closed.
+#[cfg(windows)]
+fn open_named(path: &PathBuf) -> io::Result<fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .custom_flags(FILE_FLAG_DELETE_ON_CLOSE)
+        .open(path)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn open_named(_path: &PathBuf) -> io::Result<fs::File> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "temporary files are not supported"))
+}
@@ -0,0 +1,34 @@
+//! Saving and restoring terminal settings.
+
+use std::io;
+use std::os::unix::io::RawFd;
+
+use crate::sys;
+
+/// Guard restoring the settings a terminal had when the guard was created, once dropped. See
+/// `Input::terminal_guard`.
+///
+/// Dropping happens on panics too, as long as they unwind, so settings tweaked while the guard is
+/// alive (e.g. disabling echo) are always put back.
+pub struct TerminalGuard {
+    fd: RawFd,
+    saved: sys::Termios,
+}
+
+impl TerminalGuard {
+    pub(crate) fn new(fd: RawFd) -> io::Result<Self> {
+        Ok(TerminalGuard { fd, saved: sys::get_termios(fd)? })
+    }
+
+    /// Restores the saved settings right away, returning any error. They are restored again when
+    /// the guard is dropped.
+    pub fn restore(&self) -> io::Result<()> {
+        sys::set_termios(self.fd, &self.saved)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
@@ -0,0 +1,978 @@
+//! Writer adapters backing the `Output` wrapping methods.
+
+use std::any::Any;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::crc32c::Crc32c;
+use crate::{BufferKind, HashHandle, Output};
+
+/// Writer wrapping an Output, which needs to write some final bytes once done. Boxed into
+/// `Output::Adapter`.
+///
+/// Adapters are `Any`, so that a boxed adapter can be downcast back to its concrete type.
+pub trait OutputAdapter: Write + Send + Any {
+    /// Writes out any final bytes to the inner Output. The inner Output itself is finished by the
+    /// caller afterwards.
+    ///
+    /// This may be called more than once, and should not write the final bytes again.
+    fn finish(&mut self) -> io::Result<()>;
+
+    /// Returns the wrapped Output.
+    fn inner(&self) -> &Output;
+
+    /// Returns the wrapped Output, mutably.
+    fn inner_mut(&mut self) -> &mut Output;
+
+    /// Returns whether no written bytes are waiting in a buffer to be written out. Adapters
+    /// buffering bytes until flushed must override this.
+    fn is_flushed(&self) -> bool {
+        self.inner().is_flushed()
+    }
+
+    /// Returns the kind of store backing this adapter, by default the kind of the wrapped Output.
+    fn kind(&self) -> BufferKind {
+        self.inner().kind()
+    }
+
+    /// Syncs the file this adapter writes to, for `Output::checkpoint` and `Output::close`. Only
+    /// the file contents are synced when `data_only` is set. By default, the wrapped Output is
+    /// synced; adapters holding their own file must sync it instead.
+    fn sync(&mut self, data_only: bool) -> io::Result<()> {
+        self.inner_mut().sync(data_only)
+    }
+}
+
+/// Adapter over a custom writer, reporting the kind of store the writer stands in for.
+pub(crate) struct WithKind {
+    inner: Output,
+    kind: BufferKind,
+}
+
+impl WithKind {
+    pub(crate) fn new<W: Write + Send + 'static>(writer: W, kind: BufferKind) -> Self {
+        WithKind { inner: Output::Custom(Box::new(writer)), kind }
+    }
+}
+
+impl Write for WithKind {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for WithKind {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn kind(&self) -> BufferKind {
+        self.kind
+    }
+}
+
+/// Output buffer coalescing small writes, while passing large ones straight through. See
+/// `Output::smart_buffered`.
+pub struct BufferedOutput {
+    inner: Output,
+    buf: Vec<u8>,
+    threshold: usize,
+    cap: usize,
+}
+
+impl BufferedOutput {
+    pub(crate) fn new(inner: Output, threshold: usize, cap: usize) -> Self {
+        BufferedOutput { inner, buf: Vec::with_capacity(cap), threshold, cap }
+    }
+
+    /// Writes the buffered bytes out, returning how many were written. Whatever couldn't be
+    /// written is kept in the buffer.
+    pub(crate) fn flush_buf(&mut self) -> Result<u64, (u64, io::Error)> {
+        let mut written = 0;
+        let ret = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+            match self.inner.write(&self.buf[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..written);
+        ret.map(|_| written as u64).map_err(|e| (written as u64, e))
+    }
+
+    /// Returns the wrapped Output.
+    pub(crate) fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    /// Drops the buffered bytes without writing them.
+    pub(crate) fn discard(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Returns whether the buffer is empty, and the inner Output flushed as well.
+    pub(crate) fn is_flushed(&self) -> bool {
+        self.buf.is_empty() && self.inner.is_flushed()
+    }
+
+    /// Flushes the buffered bytes then the inner Output, returning how many buffered bytes were
+    /// written out.
+    pub(crate) fn flush_counted(&mut self) -> Result<u64, (u64, io::Error)> {
+        let written = self.flush_buf()?;
+        self.inner.flush().map(|_| written).map_err(|e| (written, e))
+    }
+}
+
+impl Write for BufferedOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() >= self.threshold {
+            self.flush_buf().map_err(|(_, e)| e)?;
+            return self.inner.write(data);
+        }
+        if self.buf.len() + data.len() > self.cap {
+            self.flush_buf().map_err(|(_, e)| e)?;
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_counted().map(|_| ()).map_err(|(_, e)| e)
+    }
+}
+
+impl Drop for BufferedOutput {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+/// Writer appending a newline on finish, if the last byte written wasn't one. See
+/// `Output::ensure_trailing_newline`.
+pub(crate) struct TrailingNewline {
+    inner: Output,
+    last: Option<u8>,
+}
+
+impl TrailingNewline {
+    pub(crate) fn new(inner: Output) -> Self {
+        TrailingNewline { inner, last: None }
+    }
+}
+
+impl Write for TrailingNewline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if n > 0 {
+            self.last = Some(buf[n - 1]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for TrailingNewline {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.last.is_some() && self.last != Some(b'\n') {
+            self.inner.write_all(b"\n")?;
+            self.last = Some(b'\n');
+        }
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Handle to the bytes captured by an Output. See `Output::with_capture`.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureHandle {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CaptureHandle {
+    /// Returns a copy of the bytes captured so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.data.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Writer appending to a vector shared behind a mutex. See `Output::shared_vec`.
+pub(crate) struct SharedVec(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedVec {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer capturing every byte accepted by the inner Output.
+pub(crate) struct Capture {
+    inner: Output,
+    handle: CaptureHandle,
+}
+
+impl Capture {
+    pub(crate) fn new(inner: Output) -> (Self, CaptureHandle) {
+        let handle = CaptureHandle::default();
+        (Capture { inner, handle: handle.clone() }, handle)
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let mut data = self.handle.data.lock().unwrap_or_else(PoisonError::into_inner);
+        data.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Capture {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Writer flushing the inner Output after every write. See `Output::unbuffered`.
+pub(crate) struct Unbuffered {
+    inner: Output,
+}
+
+impl Unbuffered {
+    pub(crate) fn new(inner: Output) -> Self {
+        Unbuffered { inner }
+    }
+}
+
+impl Write for Unbuffered {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.inner.flush()?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Unbuffered {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Writer replacing control bytes with a visible `^X` notation. See `Output::sanitize_terminal`.
+pub(crate) struct SanitizeTerminal {
+    inner: Output,
+    /// Start of a UTF-8 sequence split across writes.
+    held: Vec<u8>,
+}
+
+impl SanitizeTerminal {
+    pub(crate) fn new(inner: Output) -> Self {
+        SanitizeTerminal { inner, held: vec![] }
+    }
+
+    /// Appends the visible notation of a C1 control code (0x80 to 0x9f), `M-` followed by the caret
+    /// notation of the matching C0 control code, as `cat -v` does.
+    fn push_c1(out: &mut Vec<u8>, code: u8) {
+        out.extend_from_slice(&[b'M', b'-', b'^', code - 0x80 + 0x40]);
+    }
+}
+
+impl Write for SanitizeTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = std::mem::take(&mut self.held);
+        data.extend_from_slice(buf);
+        let mut out = Vec::with_capacity(data.len());
+        let mut i = 0;
+        while i < data.len() {
+            let b = data[i];
+            let len = match b {
+                0xc2..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf4 => 4,
+                _ => 1,
+            };
+            let seq = &data[i..data.len().min(i + len)];
+            if seq.len() < len && seq[1..].iter().all(|&c| c & 0xc0 == 0x80) {
+                // Possibly valid, but cut short: wait for the rest of it.
+                self.held = seq.to_vec();
+                break;
+            }
+            match (b, std::str::from_utf8(seq)) {
+                (b'\n', _) | (b'\t', _) => out.push(b),
+                (0x00..=0x1f, _) => out.extend_from_slice(&[b'^', b + 0x40]),
+                (0x7f, _) => out.extend_from_slice(b"^?"),
+                (0x00..=0x7f, _) => out.push(b),
+                (_, Ok(c)) => match c.chars().next().map(u32::from) {
+                    // C1 control characters, encoded as UTF-8.
+                    Some(code @ 0x80..=0x9f) => Self::push_c1(&mut out, code as u8),
+                    _ => out.extend_from_slice(seq),
+                },
+                (_, Err(_)) => {
+                    // Stray byte, outside of any valid UTF-8 sequence.
+                    match b {
+                        0x80..=0x9f => Self::push_c1(&mut out, b),
+                        _ => out.push(b),
+                    }
+                    i += 1;
+                    continue;
+                }
+            }
+            i += seq.len();
+        }
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for SanitizeTerminal {
+    fn finish(&mut self) -> io::Result<()> {
+        // The sequence held back is never going to be completed: replace it as a whole.
+        if self.held.is_empty() {
+            return Ok(());
+        }
+        self.held.clear();
+        self.inner.write_all("\u{FFFD}".as_bytes())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn is_flushed(&self) -> bool {
+        self.held.is_empty() && self.inner.is_flushed()
+    }
+}
+
+/// Writer dropping lines identical to the one right before them. See `Output::uniq`.
+pub(crate) struct Uniq {
+    inner: Output,
+    line: Vec<u8>,
+    last: Option<Vec<u8>>,
+}
+
+impl Uniq {
+    pub(crate) fn new(inner: Output) -> Self {
+        Uniq { inner, line: vec![], last: None }
+    }
+
+    /// Writes out the current line and its terminator, unless it repeats the last line.
+    fn end_line(&mut self, terminator: &[u8]) -> io::Result<()> {
+        if self.last.as_ref() != Some(&self.line) {
+            self.inner.write_all(&self.line)?;
+            self.inner.write_all(terminator)?;
+            self.last = Some(std::mem::take(&mut self.line));
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Uniq {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(i) = rest.iter().position(|&b| b == b'\n') {
+            self.line.extend_from_slice(&rest[..i]);
+            self.end_line(b"\n")?;
+            rest = &rest[i + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Uniq {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        self.end_line(b"")
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn is_flushed(&self) -> bool {
+        self.line.is_empty() && self.inner.is_flushed()
+    }
+}
+
+/// Writer indenting every line. See `Output::indented`.
+pub(crate) struct Indented {
+    inner: Output,
+    level: usize,
+    unit: String,
+    line_start: bool,
+}
+
+impl Indented {
+    pub(crate) fn new(inner: Output, level: usize, unit: &str) -> Self {
+        Indented { inner, level, unit: unit.to_string(), line_start: true }
+    }
+
+    pub(crate) fn set_level(&mut self, level: usize) {
+        self.level = level;
+    }
+}
+
+impl Write for Indented {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.line_start && rest[0] != b'\n' {
+                for _ in 0..self.level {
+                    self.inner.write_all(self.unit.as_bytes())?;
+                }
+            }
+            let end = rest.iter().position(|&b| b == b'\n').map_or(rest.len(), |i| i + 1);
+            self.inner.write_all(&rest[..end])?;
+            self.line_start = rest[end - 1] == b'\n';
+            rest = &rest[end..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Indented {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Writer into a temporary file, renamed over the target path once finished. See
+/// `OutputBuilder::atomic`.
+pub(crate) struct Atomic {
+    inner: Output,
+    tmp_path: PathBuf,
+    path: PathBuf,
+    done: bool,
+}
+
+impl Atomic {
+    pub(crate) fn new(inner: Output, tmp_path: PathBuf, path: PathBuf) -> Self {
+        Atomic { inner, tmp_path, path, done: false }
+    }
+}
+
+impl Write for Atomic {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Atomic {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.inner.finish()?;
+        if let Output::File(f) = &self.inner {
+            f.sync_all()?;
+        }
+        fs::rename(&self.tmp_path, &self.path)?;
+        self.done = true;
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+impl Drop for Atomic {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Writer wrapping lines at a column width. See `Output::wrapped`.
+pub(crate) struct Wrapped {
+    inner: Output,
+    width: usize,
+    col: usize,
+    word: Vec<u8>,
+    word_cols: usize,
+}
+
+impl Wrapped {
+    pub(crate) fn new(inner: Output, width: usize) -> Self {
+        Wrapped { inner, width: width.max(1), col: 0, word: vec![], word_cols: 0 }
+    }
+
+    /// Writes out the current word, on a new line if it doesn't fit on the current one.
+    fn end_word(&mut self) -> io::Result<()> {
+        if self.word.is_empty() {
+            return Ok(());
+        }
+        if self.col > 0 && self.col + self.word_cols > self.width {
+            self.inner.write_all(b"\n")?;
+            self.col = 0;
+        }
+        self.inner.write_all(&self.word)?;
+        self.col += self.word_cols;
+        self.word.clear();
+        self.word_cols = 0;
+        Ok(())
+    }
+}
+
+impl Write for Wrapped {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            match b {
+                b'\n' => {
+                    self.end_word()?;
+                    self.inner.write_all(b"\n")?;
+                    self.col = 0;
+                }
+                b' ' | b'\t' => {
+                    self.end_word()?;
+                    if self.col >= self.width {
+                        self.inner.write_all(b"\n")?;
+                        self.col = 0;
+                    } else {
+                        self.inner.write_all(&[b])?;
+                        self.col += 1;
+                    }
+                }
+                _ => {
+                    // UTF-8 continuation bytes belong to the same column as the bytes before.
+                    let starts_char = b & 0xC0 != 0x80;
+                    if starts_char && self.word_cols == self.width {
+                        // The word doesn't fit on a line of its own, break it.
+                        self.end_word()?;
+                        self.inner.write_all(b"\n")?;
+                        self.col = 0;
+                    }
+                    self.word.push(b);
+                    if starts_char {
+                        self.word_cols += 1;
+                    }
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Wrapped {
+    fn finish(&mut self) -> io::Result<()> {
+        self.end_word()
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn is_flushed(&self) -> bool {
+        self.word.is_empty() && self.inner.is_flushed()
+    }
+}
+
+/// Writer writing to several outputs, reporting the failing ones instead of stopping at them. See
+/// `Output::broadcast`.
+pub(crate) struct Broadcast<F> {
+    outputs: Vec<Output>,
+    on_error: F,
+}
+
+impl<F: FnMut(usize, io::Error)> Broadcast<F> {
+    pub(crate) fn new(outputs: Vec<Output>, on_error: F) -> Self {
+        Broadcast { outputs, on_error }
+    }
+
+    /// Runs `op` on every output, succeeding unless it failed on all of them.
+    fn each(&mut self, mut op: impl FnMut(&mut Output) -> io::Result<()>) -> io::Result<()> {
+        let mut failed = 0;
+        for (i, output) in self.outputs.iter_mut().enumerate() {
+            if let Err(e) = op(output) {
+                (self.on_error)(i, e);
+                failed += 1;
+            }
+        }
+        if failed > 0 && failed == self.outputs.len() {
+            return Err(io::Error::other("every broadcast output failed"));
+        }
+        Ok(())
+    }
+}
+
+impl<F: FnMut(usize, io::Error)> Write for Broadcast<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.each(|output| output.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.each(|output| output.flush())
+    }
+}
+
+/// File appended to under an exclusive lock for every write. See `Output::file_append_locked`.
+///
+/// The file is kept as the wrapped Output, so that syncing and the file descriptor helpers reach
+/// it.
+pub(crate) struct LockedAppend {
+    inner: Output,
+}
+
+impl LockedAppend {
+    pub(crate) fn new(file: fs::File) -> Self {
+        LockedAppend { inner: Output::File(file) }
+    }
+}
+
+impl Write for LockedAppend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Output::File(file) = &mut self.inner else {
+            unreachable!("locked appends always wrap a file")
+        };
+        file.lock()?;
+        let ret = file.write_all(buf);
+        let unlocked = file.unlock();
+        ret.and(unlocked).map(|_| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for LockedAppend {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Writer appending the CRC32C of everything written on finish. See `Output::with_crc_footer`.
+pub(crate) struct CrcFooter {
+    inner: Output,
+    crc: Crc32c,
+    done: bool,
+}
+
+impl CrcFooter {
+    pub(crate) fn new(inner: Output) -> Self {
+        CrcFooter { inner, crc: Crc32c::new(), done: false }
+    }
+}
+
+impl Write for CrcFooter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for CrcFooter {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.inner.write_all(&self.crc.value().to_le_bytes())?;
+        self.done = true;
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
+
+/// Writer only opening its file on the first write. See `Output::lazy_file`.
+pub(crate) struct LazyFile {
+    /// Path of the file, until the first write opens it.
+    path: Option<PathBuf>,
+    /// The opened file, or a sink standing in for it until then.
+    inner: Output,
+}
+
+impl LazyFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        LazyFile { path: Some(path), inner: Output::Custom(Box::new(io::sink())) }
+    }
+}
+
+impl Write for LazyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(path) = &self.path {
+            self.inner = Output::file(path)?;
+            self.path = None;
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for LazyFile {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn kind(&self) -> BufferKind {
+        BufferKind::File
+    }
+
+    fn sync(&mut self, data_only: bool) -> io::Result<()> {
+        match self.path {
+            // Nothing was written, so there is no file to sync yet.
+            Some(_) => Ok(()),
+            None => self.inner.sync(data_only),
+        }
+    }
+}
+
+/// Writer aligning the columns of tab-separated rows. See `Output::table`.
+pub(crate) struct Table {
+    inner: Output,
+    rows: Vec<Vec<u8>>,
+    line: Vec<u8>,
+}
+
+impl Table {
+    pub(crate) fn new(inner: Output) -> Self {
+        Table { inner, rows: vec![], line: vec![] }
+    }
+
+    /// Writes out the buffered rows, with their columns padded to the width of the widest cell.
+    fn write_rows(&mut self) -> io::Result<()> {
+        let width = |cell: &[u8]| cell.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        let mut widths: Vec<usize> = vec![];
+        for row in &self.rows {
+            for (i, cell) in row.split(|&b| b == b'\t').enumerate() {
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width(cell)),
+                    None => widths.push(width(cell)),
+                }
+            }
+        }
+        let mut out = vec![];
+        for row in self.rows.drain(..) {
+            let cells: Vec<&[u8]> = row.split(|&b| b == b'\t').collect();
+            for (i, cell) in cells.iter().enumerate() {
+                out.extend_from_slice(cell);
+                if i + 1 < cells.len() {
+                    out.resize(out.len() + widths[i] - width(cell) + 2, b' ');
+                }
+            }
+            out.push(b'\n');
+        }
+        self.inner.write_all(&out)
+    }
+}
+
+impl Write for Table {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(i) = rest.iter().position(|&b| b == b'\n') {
+            self.line.extend_from_slice(&rest[..i]);
+            self.rows.push(std::mem::take(&mut self.line));
+            rest = &rest[i + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_rows()?;
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for Table {
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.rows.push(std::mem::take(&mut self.line));
+        }
+        self.write_rows()
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn is_flushed(&self) -> bool {
+        self.rows.is_empty() && self.line.is_empty() && self.inner.is_flushed()
+    }
+}
+
+/// Writer hashing every byte accepted by the inner Output. See `Output::running_hash`.
+pub(crate) struct RunningHash {
+    inner: Output,
+    handle: HashHandle,
+}
+
+impl RunningHash {
+    pub(crate) fn new(inner: Output, handle: HashHandle) -> Self {
+        RunningHash { inner, handle }
+    }
+
+    pub(crate) fn current_digest(&self) -> Vec<u8> {
+        self.handle.current_digest()
+    }
+}
+
+impl Write for RunningHash {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.handle.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for RunningHash {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+}
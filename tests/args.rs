@@ -0,0 +1,103 @@
+//! The `from_arg` family of constructors.
+
+mod common;
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use wbuf::{BufferError, Input, Output};
+
+fn read_all(mut input: Input) -> Vec<u8> {
+    let mut data = vec![];
+    input.read_to_end(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn from_arg_ext_encoding_suffix() {
+    let dir = common::TempDir::new();
+    let path = dir.file("text.txt");
+    let utf16: Vec<u8> = "héllo €".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    fs::write(&path, utf16).unwrap();
+
+    let input = Input::from_arg_ext(Some(&format!("{}#utf-16le", path))).unwrap();
+    assert_eq!(read_all(input), "héllo €".as_bytes());
+
+    let err = Input::from_arg_ext(Some(&format!("{}#ebcdic", path))).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("unknown encoding"), "{}", err);
+}
+
+#[test]
+fn from_arg_ext_existing_path_with_hash() {
+    let dir = common::TempDir::new();
+    let path = dir.file("bug#42");
+    fs::write(&path, b"issue").unwrap();
+    assert_eq!(read_all(Input::from_arg_ext(Some(&path)).unwrap()), b"issue");
+}
+
+#[test]
+fn from_arg_ext_inline_data() {
+    assert_eq!(read_all(Input::from_arg_ext(Some("data:hi")).unwrap()), b"hi");
+    assert_eq!(read_all(Input::from_arg_ext(Some("data:")).unwrap()), b"");
+    let input = Input::from_arg_ext(Some("data:base64,AP9oaQ==")).unwrap();
+    assert_eq!(read_all(input), b"\x00\xffhi");
+
+    let err = Input::from_arg_ext(Some("data:base64,a*")).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn from_arg_checked_open_error() {
+    let dir = common::TempDir::new();
+    let path = dir.file("missing/file.txt");
+    let err = Input::from_arg_checked(Some(&path)).err().unwrap();
+    match &err {
+        BufferError::Open { path: p, source } => {
+            assert_eq!(p, Path::new(&path));
+            assert_eq!(source.kind(), io::ErrorKind::NotFound);
+        }
+        other => panic!("expected an open error, got {:?}", other),
+    }
+    assert!(err.to_string().contains("file.txt"));
+    let err = io::Error::from(err);
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let err = Output::from_arg_checked(Some(&path)).err().unwrap();
+    assert!(matches!(err, BufferError::Open { .. }));
+}
+
+#[cfg(unix)]
+#[test]
+fn from_arg_os_non_utf8_path() {
+    use std::ffi::OsStr;
+    use std::io::Write;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = common::TempDir::new();
+    let path = dir.path().join(OsStr::from_bytes(b"caf\xe9.txt"));
+    fs::write(&path, "raw").unwrap();
+    assert!(path.to_str().is_none());
+    let input = Input::from_arg_os(Some(path.as_os_str())).unwrap();
+    assert_eq!(read_all(input), b"raw");
+
+    let mut out = Output::from_arg_os(Some(path.as_os_str())).unwrap();
+    out.write_all(b"new").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+}
+
+#[test]
+fn from_arg_literal_dash_is_a_file() {
+    assert!(Input::from_arg(Some("-")).unwrap().as_stdin().is_some());
+    // Tests run from the crate root, which has no file named "-".
+    assert!(!Path::new("-").exists());
+    let err = Input::from_arg_literal("-").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let dir = common::TempDir::new();
+    let path = dir.file("-");
+    fs::write(&path, b"dash").unwrap();
+    assert_eq!(read_all(Input::from_arg_literal(&path).unwrap()), b"dash");
+}
@@ -0,0 +1,73 @@
+//! `Input::builder` and `Output::builder`.
+
+mod common;
+
+use std::fs;
+use std::io::{Read, Write};
+
+use wbuf::{Input, Output};
+
+#[test]
+fn input_builder_buffered() {
+    let dir = common::TempDir::new();
+    let path = dir.join("in.txt");
+    fs::write(&path, b"buffered contents").unwrap();
+    let mut input = Input::builder(&path).buffered(4).open().unwrap();
+    let mut s = String::new();
+    input.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "buffered contents");
+}
+
+#[cfg(unix)]
+#[test]
+fn input_builder_no_follow() {
+    let dir = common::TempDir::new();
+    let target = dir.join("target.txt");
+    let link = dir.join("link.txt");
+    fs::write(&target, b"target").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    assert!(Input::builder(&link).no_follow(true).open().is_err());
+    let mut s = String::new();
+    Input::builder(&link).no_follow(false).open().unwrap().read_to_string(&mut s).unwrap();
+    assert_eq!(s, "target");
+}
+
+#[test]
+fn output_builder_atomic_buffered_create_dirs() {
+    let dir = common::TempDir::new();
+    let path = dir.join("a/b/out.txt");
+    let mut out =
+        Output::builder(&path).atomic(true).buffered(4).create_dirs(true).open().unwrap();
+    out.write_all(b"atomic contents").unwrap();
+    assert!(!path.exists());
+    out.finish().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"atomic contents");
+    assert_eq!(fs::read_dir(dir.join("a/b")).unwrap().count(), 1);
+}
+
+#[test]
+fn output_builder_writes_like_file() {
+    let dir = common::TempDir::new();
+    let path = dir.join("out.txt");
+    fs::write(&path, b"hello").unwrap();
+    Output::builder(&path).open().unwrap().write_all(b"HI").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"HIllo");
+    Output::builder(&path).truncate(true).open().unwrap().write_all(b"hi").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"hi");
+}
+
+#[cfg(unix)]
+#[test]
+fn output_builder_opens_write_only_files() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = common::TempDir::new();
+    let path = dir.join("write-only.txt");
+    let mut out = Output::builder(&path).mode(0o200).open().unwrap();
+    out.write_all(b"first").unwrap();
+    drop(out);
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o200);
+    Output::builder(&path).open().unwrap().write_all(b"F").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"First");
+}
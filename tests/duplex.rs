@@ -0,0 +1,178 @@
+//! InputOutput constructors and wrappers.
+
+mod common;
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use wbuf::{BufferKind, InputOutput, RetryPolicy};
+
+/// Duplex stream returning scripted read results, one per read, and recording what's written.
+struct Scripted {
+    reads: VecDeque<io::Result<Vec<u8>>>,
+    written: Vec<u8>,
+}
+
+impl Scripted {
+    fn new(reads: Vec<io::Result<&[u8]>>) -> Self {
+        let reads = reads.into_iter().map(|r| r.map(<[u8]>::to_vec)).collect();
+        Scripted { reads, written: vec![] }
+    }
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reads.pop_front() {
+            None => Ok(0),
+            Some(Err(e)) => Err(e),
+            Some(Ok(data)) => {
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+        }
+    }
+}
+
+impl Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn would_block<T>() -> io::Result<T> {
+    Err(io::ErrorKind::WouldBlock.into())
+}
+
+#[test]
+fn read_line_nonblocking_partial_lines() {
+    let scripted = Scripted::new(vec![Ok(b"he"), would_block(), Ok(b"llo\nwor"), would_block()]);
+    let mut duplex = InputOutput::Custom(Box::new(scripted));
+    let mut line = String::new();
+    assert_eq!(duplex.read_line_nonblocking(&mut line).unwrap(), None);
+    assert_eq!(line, "");
+    assert_eq!(duplex.read_line_nonblocking(&mut line).unwrap(), Some(6));
+    assert_eq!(line, "hello\n");
+    line.clear();
+    assert_eq!(duplex.read_line_nonblocking(&mut line).unwrap(), None);
+    assert_eq!(duplex.read_line_nonblocking(&mut line).unwrap(), Some(3));
+    assert_eq!(line, "wor");
+    assert_eq!(duplex.read_line_nonblocking(&mut line).unwrap(), Some(0));
+}
+
+#[test]
+fn read_line_nonblocking_invalid_utf8_stays_pending() {
+    let mut duplex = InputOutput::Custom(Box::new(Scripted::new(vec![Ok(b"\xff\xfe\n")])));
+    let mut line = String::new();
+    let err = duplex.read_line_nonblocking(&mut line).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let mut rest = vec![];
+    duplex.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"\xff\xfe\n");
+}
+
+#[test]
+fn temp_round_trip() {
+    let mut temp = InputOutput::temp().unwrap();
+    temp.write_all(b"scratch data").unwrap();
+    temp.seek(SeekFrom::Start(0)).unwrap();
+    let mut data = String::new();
+    temp.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "scratch data");
+}
+
+#[test]
+fn temp_with_path() {
+    let (mut temp, path) = InputOutput::temp_with_path().unwrap();
+    temp.write_all(b"x").unwrap();
+    if cfg!(unix) {
+        assert_eq!(path, None);
+    } else {
+        let path = path.unwrap();
+        assert!(path.starts_with(std::env::temp_dir()));
+        assert!(path.exists());
+        drop(temp);
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+fn with_retry_retries_transient_errors() {
+    let policy = RetryPolicy::new(3, Duration::from_millis(1));
+    let scripted = Scripted::new(vec![would_block(), would_block(), Ok(b"data")]);
+    let mut duplex = InputOutput::Custom(Box::new(scripted)).with_retry(policy);
+    let mut buf = [0; 8];
+    assert_eq!(duplex.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"data");
+
+    // Giving up after the last attempt.
+    let scripted = Scripted::new(vec![would_block(), would_block(), would_block(), Ok(b"x")]);
+    let mut duplex = InputOutput::Custom(Box::new(scripted)).with_retry(policy);
+    assert_eq!(duplex.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+    // Other errors aren't retried.
+    let denied = Err(io::ErrorKind::PermissionDenied.into());
+    let scripted = Scripted::new(vec![denied, Ok(b"x")]);
+    let mut duplex = InputOutput::Custom(Box::new(scripted)).with_retry(policy);
+    assert_eq!(duplex.read(&mut buf).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(duplex.read(&mut buf).unwrap(), 1);
+}
+
+#[test]
+fn kind_per_constructor() {
+    let dir = common::TempDir::new();
+    std::fs::write(dir.join("io"), "").unwrap();
+    assert_eq!(InputOutput::stdio().kind(), BufferKind::Standard);
+    assert_eq!(InputOutput::memory().kind(), BufferKind::Memory);
+    assert_eq!(InputOutput::file(dir.join("io")).unwrap().kind(), BufferKind::File);
+    assert_eq!(InputOutput::temp().unwrap().kind(), BufferKind::File);
+    let scripted = Scripted { reads: VecDeque::new(), written: vec![] };
+    assert_eq!(InputOutput::Custom(Box::new(scripted)).kind(), BufferKind::Custom);
+}
+
+#[test]
+fn file_read_after_write() {
+    let dir = common::TempDir::new();
+    std::fs::write(dir.join("io"), "").unwrap();
+    let mut duplex = InputOutput::file(dir.join("io")).unwrap();
+    duplex.write_all(b"abc").unwrap();
+    duplex.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = [0; 1];
+    duplex.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"a");
+
+    // Switching between reads and writes needs no flush or seek in between.
+    duplex.write_all(b"X").unwrap();
+    let mut rest = String::new();
+    duplex.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "c");
+    duplex.seek(SeekFrom::Start(0)).unwrap();
+    let mut data = String::new();
+    duplex.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "aXc");
+}
+
+#[test]
+fn with_trace_sees_bytes_exchanged() {
+    let mut duplex = InputOutput::memory();
+    duplex.write_all(b"ping").unwrap();
+    duplex.seek(SeekFrom::Start(0)).unwrap();
+    let (reads, writes) = (Arc::new(Mutex::new(vec![])), Arc::new(Mutex::new(vec![])));
+    let (r, w) = (reads.clone(), writes.clone());
+    let mut duplex = duplex.with_trace(
+        move |data| r.lock().unwrap().push(data.to_vec()),
+        move |data| w.lock().unwrap().push(data.to_vec()),
+    );
+    let mut buf = [0; 2];
+    duplex.read_exact(&mut buf).unwrap();
+    duplex.write_all(b"NG").unwrap();
+    assert_eq!(duplex.read(&mut buf).unwrap(), 0);
+    assert_eq!(*reads.lock().unwrap(), [b"pi".to_vec(), vec![]]);
+    assert_eq!(*writes.lock().unwrap(), [b"NG".to_vec()]);
+}
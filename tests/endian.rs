@@ -0,0 +1,84 @@
+//! Typed integer reading and writing.
+
+use std::io;
+
+use wbuf::{Input, Output};
+
+#[test]
+fn fixed_width_round_trip() {
+    let mut out = Output::memory();
+    out.write_u16_le(0x0102).unwrap();
+    out.write_u16_be(0x0102).unwrap();
+    out.write_u32_le(0x0102_0304).unwrap();
+    out.write_u32_be(0x0102_0304).unwrap();
+    out.write_u64_le(0x0102_0304_0506_0708).unwrap();
+    out.write_u64_be(0x0102_0304_0506_0708).unwrap();
+    out.write_i16_le(-2).unwrap();
+    out.write_i32_be(-2).unwrap();
+    out.write_i64_le(i64::MIN).unwrap();
+    let data = out.into_vec().unwrap();
+    assert_eq!(&data[..12], b"\x02\x01\x01\x02\x04\x03\x02\x01\x01\x02\x03\x04");
+    assert_eq!(&data[12..28], b"\x08\x07\x06\x05\x04\x03\x02\x01\x01\x02\x03\x04\x05\x06\x07\x08");
+
+    let mut input = Input::memory_from(data);
+    assert_eq!(input.read_u16_le().unwrap(), 0x0102);
+    assert_eq!(input.read_u16_be().unwrap(), 0x0102);
+    assert_eq!(input.read_u32_le().unwrap(), 0x0102_0304);
+    assert_eq!(input.read_u32_be().unwrap(), 0x0102_0304);
+    assert_eq!(input.read_u64_le().unwrap(), 0x0102_0304_0506_0708);
+    assert_eq!(input.read_u64_be().unwrap(), 0x0102_0304_0506_0708);
+    assert_eq!(input.read_i16_le().unwrap(), -2);
+    assert_eq!(input.read_i32_be().unwrap(), -2);
+    assert_eq!(input.read_i64_le().unwrap(), i64::MIN);
+    assert_eq!(input.read_u16_be().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn varint_vectors() {
+    let vectors: [(u64, &[u8]); 4] = [
+        (0, b"\x00"),
+        (127, b"\x7f"),
+        (128, b"\x80\x01"),
+        (u64::MAX, b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"),
+    ];
+    for (value, bytes) in vectors {
+        let mut out = Output::memory();
+        out.write_varint(value).unwrap();
+        assert_eq!(out.into_vec().unwrap(), bytes);
+        assert_eq!(Input::memory_from(bytes).read_varint().unwrap(), value);
+    }
+
+    // Longer than 10 bytes, then overflowing a u64 on the 10th byte.
+    let invalid: [&[u8]; 2] = [
+        b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x80\x00",
+        b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02",
+    ];
+    for bytes in invalid {
+        let err = Input::memory_from(bytes).read_varint().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn signed_round_trip_both_orders() {
+    let mut out = Output::memory();
+    out.write_i16_le(-2).unwrap();
+    out.write_i16_be(-2).unwrap();
+    out.write_i32_le(-0x0102_0304).unwrap();
+    out.write_i32_be(-0x0102_0304).unwrap();
+    out.write_i64_le(-0x0102_0304_0506_0708).unwrap();
+    out.write_i64_be(-0x0102_0304_0506_0708).unwrap();
+    let data = out.into_vec().unwrap();
+    assert_eq!(&data[..4], b"\xfe\xff\xff\xfe");
+    assert_eq!(&data[4..12], b"\xfc\xfc\xfd\xfe\xfe\xfd\xfc\xfc");
+    assert_eq!(&data[12..20], b"\xf8\xf8\xf9\xfa\xfb\xfc\xfd\xfe");
+    assert_eq!(&data[20..], b"\xfe\xfd\xfc\xfb\xfa\xf9\xf8\xf8");
+
+    let mut input = Input::memory_from(data);
+    assert_eq!(input.read_i16_le().unwrap(), -2);
+    assert_eq!(input.read_i16_be().unwrap(), -2);
+    assert_eq!(input.read_i32_le().unwrap(), -0x0102_0304);
+    assert_eq!(input.read_i32_be().unwrap(), -0x0102_0304);
+    assert_eq!(input.read_i64_le().unwrap(), -0x0102_0304_0506_0708);
+    assert_eq!(input.read_i64_be().unwrap(), -0x0102_0304_0506_0708);
+}
@@ -0,0 +1,72 @@
+//! File descriptor arguments to the `from_arg` constructors. These tests change the working
+//! directory, so they live in their own test binary.
+#![cfg(unix)]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::process::Command;
+
+use wbuf::{Input, Output};
+
+#[test]
+fn from_arg_fd() {
+    let dir = common::TempDir::new();
+    env::set_current_dir(dir.path()).unwrap();
+
+    // A pipe's read end, passed both bare and as /dev/fd/N.
+    for prefix in &["", "/dev/fd/"] {
+        let (mut input, mut output) = wbuf::pipe().unwrap();
+        let fd = match &input {
+            Input::File(f) => f.as_raw_fd(),
+            _ => unreachable!(),
+        };
+        output.write_all(b"piped").unwrap();
+        drop(output);
+        let mut arg_input = Input::from_arg(Some(&format!("{}{}", prefix, fd))).unwrap();
+        let mut s = String::new();
+        arg_input.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "piped");
+        drop(arg_input);
+        // The descriptor was duplicated, so the original is still usable.
+        assert_eq!(input.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    // A number which isn't an open descriptor is a file name.
+    fs::write("4242", b"from file").unwrap();
+    let mut s = String::new();
+    Input::from_arg(Some("4242")).unwrap().read_to_string(&mut s).unwrap();
+    assert_eq!(s, "from file");
+
+    // The standard descriptors are never taken over: "1" is a file, and stdout stays open.
+    let mut out = Output::from_arg(Some("1")).unwrap();
+    out.write_all(b"not stdout").unwrap();
+    drop(out);
+    assert_eq!(fs::read("1").unwrap(), b"not stdout");
+    assert!(io::stdout().flush().is_ok());
+
+    env::set_current_dir(env::temp_dir()).unwrap();
+}
+
+#[test]
+fn stdin_checked_closed() {
+    // The Rust runtime reopens a closed stdin before main, so stdin is closed from within a child
+    // running this test alone.
+    if env::var_os("WBUF_CLOSE_STDIN").is_some() {
+        drop(unsafe { OwnedFd::from_raw_fd(0) });
+        let err = Input::stdin_checked().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        return;
+    }
+    assert!(Input::stdin_checked().is_ok());
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "stdin_checked_closed", "--test-threads=1"])
+        .env("WBUF_CLOSE_STDIN", "1")
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+}
@@ -0,0 +1,47 @@
+//! Opening buffers through a `FileSystem`.
+
+mod common;
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use wbuf::{FileSystem, Input, MemFs, Output, RealFs};
+
+#[test]
+fn mem_fs_round_trip() {
+    let mem_fs = MemFs::new();
+    let mut out = Output::file_in(&mem_fs, "x").unwrap();
+    out.write_all(b"hello").unwrap();
+    drop(out);
+    assert!(!Path::new("x").exists());
+
+    let mut data = String::new();
+    Input::file_in(&mem_fs, "x").unwrap().read_to_string(&mut data).unwrap();
+    assert_eq!(data, "hello");
+
+    // Opening for writing again keeps the contents and writes over them from the start, as with
+    // `RealFs`, and clones share the same files.
+    Output::file_in(&mem_fs.clone(), "x").unwrap().write_all(b"HI").unwrap();
+    let mut data = String::new();
+    Input::file_in(&mem_fs, "x").unwrap().read_to_string(&mut data).unwrap();
+    assert_eq!(data, "HIllo");
+
+    let err = Input::file_in(&mem_fs, "y").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn mem_fs_writes_like_real_fs() {
+    let dir = common::TempDir::new();
+    let path = dir.join("x");
+    let mem_fs = MemFs::new();
+    let filesystems: [&dyn FileSystem; 2] = [&RealFs, &mem_fs];
+    for fs in filesystems {
+        for data in [&b"hello"[..], b"HI"] {
+            fs.open_write(&path).unwrap().write_all(data).unwrap();
+        }
+        let mut data = String::new();
+        fs.open_read(&path).unwrap().read_to_string(&mut data).unwrap();
+        assert_eq!(data, "HIllo");
+    }
+}
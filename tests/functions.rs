@@ -0,0 +1,92 @@
+//! Free functions working over several buffers.
+
+mod common;
+
+use std::fs;
+use std::io::{Read, Write};
+use std::thread;
+
+use wbuf::{Input, Output};
+
+#[test]
+fn concat_memory_outputs() {
+    let mut outputs = vec![Output::memory(), Output::from_vec(b"two ".to_vec()), Output::memory()];
+    outputs[0].write_all(b"one ").unwrap();
+    outputs[2].write_all(b"three").unwrap();
+    assert_eq!(wbuf::concat_memory(&outputs).unwrap(), b"one two three");
+    assert_eq!(wbuf::concat_memory(&[]).unwrap(), b"");
+
+    let dir = common::TempDir::new();
+    outputs.push(Output::file(dir.join("out")).unwrap());
+    assert_eq!(wbuf::concat_memory(&outputs), None);
+}
+
+#[test]
+fn copy_with_progress_reports_total() {
+    let data: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+    let mut input = Input::memory_from(data.clone());
+    let mut output = Output::memory();
+    let mut reports = vec![];
+    let copied = wbuf::copy_with_progress(&mut input, &mut output, |n, total| {
+        reports.push((n, total));
+    })
+    .unwrap();
+    assert_eq!(copied, data.len() as u64);
+    assert!(reports.len() > 1);
+    assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(reports.last(), Some(&(copied, Some(copied))));
+    assert_eq!(output.into_vec().unwrap(), data);
+}
+
+#[cfg(unix)]
+#[test]
+fn same_file_detects_aliases() {
+    let dir = common::TempDir::new();
+    let path = dir.join("data");
+    let output = Output::file(&path).unwrap();
+    let input = Input::file(&path).unwrap();
+    assert!(wbuf::same_file(&input, &output).unwrap());
+
+    let other = Output::file(dir.join("other")).unwrap();
+    assert!(!wbuf::same_file(&input, &other).unwrap());
+    assert!(!wbuf::same_file(&Input::memory_from("data"), &output).unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn copy_file_between_files() {
+    let dir = common::TempDir::new();
+    let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("src"), &data).unwrap();
+    let src = Input::file(dir.join("src")).unwrap();
+    let mut dst = Output::file(dir.join("dst")).unwrap();
+    assert_eq!(wbuf::copy_file(&src, &mut dst).unwrap(), data.len() as u64);
+    drop(dst);
+    assert_eq!(fs::read(dir.join("dst")).unwrap(), data);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn copy_file_to_appending_output() {
+    let dir = common::TempDir::new();
+    fs::write(dir.join("src"), b"appended").unwrap();
+    fs::write(dir.join("dst"), b"kept, ").unwrap();
+    let src = Input::file(dir.join("src")).unwrap();
+    let mut dst = Output::builder(dir.join("dst")).append(true).open().unwrap();
+    assert_eq!(wbuf::copy_file(&src, &mut dst).unwrap(), 8);
+    drop(dst);
+    assert_eq!(fs::read(dir.join("dst")).unwrap(), b"kept, appended");
+}
+
+#[test]
+fn pipe_round_trip() {
+    let (mut input, mut output) = wbuf::pipe().unwrap();
+    let reader = thread::spawn(move || {
+        let mut data = String::new();
+        input.read_to_string(&mut data).unwrap();
+        data
+    });
+    output.write_all(b"abc").unwrap();
+    drop(output);
+    assert_eq!(reader.join().unwrap(), "abc");
+}
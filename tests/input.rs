@@ -0,0 +1,656 @@
+//! Input constructors and wrappers.
+
+mod common;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wbuf::{
+    BufferError, BufferKind, Cancelled, EofBehavior, HashAlgorithm, Input, LineTerminator,
+};
+
+/// Reader returning one byte per read, after a delay, and counting the bytes it returned.
+struct Trickle {
+    delay: Duration,
+    sent: Arc<AtomicUsize>,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(self.delay);
+        buf[0] = b'a' + (self.sent.fetch_add(1, Ordering::SeqCst) % 26) as u8;
+        Ok(1)
+    }
+}
+
+/// Reader returning the given chunks, or errors, one per read.
+struct Scripted(VecDeque<io::Result<&'static [u8]>>);
+
+impl Scripted {
+    fn input(script: Vec<io::Result<&'static [u8]>>) -> Input {
+        Input::Custom(Box::new(Scripted(script.into())))
+    }
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.pop_front() {
+            Some(Ok(chunk)) => {
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(0),
+        }
+    }
+}
+
+fn read_string(mut input: Input) -> String {
+    let mut s = String::new();
+    input.read_to_string(&mut s).unwrap();
+    s
+}
+
+#[test]
+fn min_rate_trips_without_losing_bytes() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let trickle = Trickle { delay: Duration::from_millis(10), sent: sent.clone() };
+    let mut input = Input::Custom(Box::new(trickle)).min_rate(1000, Duration::from_millis(50));
+    let mut received = vec![];
+    let err = loop {
+        let mut buf = [0; 16];
+        match input.read(&mut buf) {
+            Ok(n) => received.extend_from_slice(&buf[..n]),
+            Err(e) => break e,
+        }
+        assert!(received.len() < 100, "guard never tripped");
+    };
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(received.len(), sent.load(Ordering::SeqCst));
+    assert!(received.iter().enumerate().all(|(i, &b)| b == b'a' + (i % 26) as u8));
+    assert_eq!(input.read(&mut [0; 16]).unwrap_err().kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn min_rate_fast_enough() {
+    let mut input = Input::memory_from(vec![7; 4096]).min_rate(1, Duration::from_millis(1));
+    let mut data = vec![];
+    input.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len(), 4096);
+}
+
+#[test]
+fn chunks_fixed_size() {
+    let input = Input::memory_from((0..10).collect::<Vec<u8>>());
+    let chunks: Vec<_> = input.chunks(4).collect::<io::Result<_>>().unwrap();
+    assert_eq!(chunks, [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+}
+
+/// Reader returning `step` bytes per read, failing with `WouldBlock` before each of them.
+struct Stalling {
+    data: &'static [u8],
+    step: usize,
+    stall: bool,
+}
+
+impl Read for Stalling {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stall = !self.stall;
+        if self.stall && !self.data.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let n = self.step.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn chunks_keep_partial_chunk_on_error() {
+    let stalling = Stalling { data: b"abcdef", step: 2, stall: false };
+    let mut chunks = Input::Custom(Box::new(stalling)).chunks(3);
+    let mut got = vec![];
+    for chunk in &mut chunks {
+        match chunk {
+            Ok(chunk) => got.push(chunk),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+        }
+    }
+    assert_eq!(got, [b"abc", b"def"]);
+}
+
+#[test]
+fn base64_decoding() {
+    let mut input = Input::memory_from("aGVsbG8g\nd29ybGQ=\n").base64();
+    let mut s = String::new();
+    input.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "hello world");
+}
+
+#[test]
+fn base64_sticky_error() {
+    // "hello world!" over and over, with a character outside the alphabet halfway through.
+    let mut data = b"aGVsbG8gd29ybGQh".repeat(100);
+    data[800] = b'*';
+    let mut input = Input::memory_from(data).max_read_size(64).base64();
+    let mut decoded = vec![];
+    let mut buf = [0; 16];
+    let err = loop {
+        match input.read(&mut buf) {
+            Ok(n) => decoded.extend_from_slice(&buf[..n]),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(!decoded.is_empty() && decoded.len() < 600);
+    assert!(decoded.starts_with(b"hello world!hello world!"));
+    for _ in 0..3 {
+        assert_eq!(input.read(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+    let taken = input.take_error().unwrap();
+    assert_eq!(taken.kind(), io::ErrorKind::InvalidData);
+    assert!(taken.to_string().contains("invalid base64"), "{}", taken);
+    assert!(input.take_error().is_none());
+    assert!(input.read(&mut buf).is_err());
+}
+
+#[test]
+fn transcode_sticky_error() {
+    // A lone low surrogate in the middle of UTF-16LE text.
+    let data = [b'o', 0, b'k', 0, 0x00, 0xdc, b'n', 0, b'o', 0];
+    let mut input = Input::memory_from(data.to_vec()).transcode("utf-16le").unwrap();
+    let mut s = String::new();
+    assert_eq!(input.read_to_string(&mut s).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(input.read(&mut [0; 8]).is_err());
+    assert!(input.take_error().is_some());
+    assert!(Input::memory_from("plain").take_error().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn readable_bytes_pipe() {
+    use std::io::Write;
+    let (input, mut output) = wbuf::pipe().unwrap();
+    assert_eq!(input.readable_bytes().unwrap(), 0);
+    output.write_all(b"12345").unwrap();
+    assert_eq!(input.readable_bytes().unwrap(), 5);
+}
+
+#[cfg(unix)]
+#[test]
+fn readable_bytes_memory() {
+    let mut input = Input::memory_from("abcdef");
+    input.read_exact(&mut [0; 2]).unwrap();
+    assert_eq!(input.readable_bytes().unwrap(), 4);
+}
+
+#[test]
+fn lines_with_terminators() {
+    let mut input = Input::memory_from("a\nb\r\nc");
+    let lines: Vec<_> = input.lines_with_terminators().collect::<io::Result<_>>().unwrap();
+    assert_eq!(
+        lines,
+        [
+            ("a".to_string(), LineTerminator::Lf),
+            ("b".to_string(), LineTerminator::CrLf),
+            ("c".to_string(), LineTerminator::None),
+        ]
+    );
+
+    let mut input = Input::memory_from("\n\n");
+    let lines: Vec<_> = input.lines_with_terminators().collect::<io::Result<_>>().unwrap();
+    assert_eq!(lines, [(String::new(), LineTerminator::Lf), (String::new(), LineTerminator::Lf)]);
+}
+
+#[test]
+fn read_all_string_lossy() {
+    let mut input = Input::memory_from(&b"ab\xffcd\xe2\x82"[..]);
+    assert_eq!(input.read_all_string_lossy().unwrap(), "ab\u{fffd}cd\u{fffd}");
+    let mut input = Input::memory_from("héllo");
+    assert_eq!(input.read_all_string_lossy().unwrap(), "héllo");
+}
+
+#[test]
+fn read_header() {
+    let mut input = Input::memory_from("HEADbody");
+    let (header, mut rest) = input.read_header(4).unwrap();
+    assert_eq!(header, b"HEAD");
+    let mut body = String::new();
+    rest.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "body");
+
+    let mut input = Input::memory_from("HE");
+    assert_eq!(input.read_header(4).err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn with_prefix() {
+    let mut input = Input::memory_from(&b"world"[..]).with_prefix(b"hello ".to_vec());
+    let mut data = String::new();
+    input.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "hello world");
+
+    let mut input = Input::memory_from("HEADbody");
+    let (header, rest) = input.read_header(4).unwrap();
+    let mut data = String::new();
+    rest.with_prefix(header).read_to_string(&mut data).unwrap();
+    assert_eq!(data, "HEADbody");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn advise_file() {
+    let dir = common::TempDir::new();
+    let path = dir.join("data");
+    fs::write(&path, vec![b'x'; 1 << 16]).unwrap();
+    let input = Input::file(&path).unwrap();
+    input.advise_sequential().unwrap();
+    input.advise_willneed().unwrap();
+    Input::memory_from("data").advise_sequential().unwrap();
+}
+
+#[test]
+fn split_at() {
+    let dir = common::TempDir::new();
+    let path = dir.join("digits");
+    fs::write(&path, "0123456789").unwrap();
+    let (mut head, mut tail) = Input::split_at(&path, 4).unwrap();
+    let (mut a, mut b) = (String::new(), String::new());
+    tail.read_to_string(&mut b).unwrap();
+    head.read_to_string(&mut a).unwrap();
+    assert_eq!((a.as_str(), b.as_str()), ("0123", "456789"));
+
+    let (mut head, mut tail) = Input::split_at(&path, 20).unwrap();
+    let (mut a, mut b) = (String::new(), String::new());
+    head.read_to_string(&mut a).unwrap();
+    tail.read_to_string(&mut b).unwrap();
+    assert_eq!((a.as_str(), b.as_str()), ("0123456789", ""));
+}
+
+#[test]
+fn read_range() {
+    let mut input = Input::memory_from("abcdef");
+    assert_eq!(input.read_range(2, 3).unwrap(), b"cde");
+    assert_eq!(input.read_range(0, 2).unwrap(), b"ab");
+    assert_eq!(input.read_range(4, 10).unwrap(), b"ef");
+    assert_eq!(input.read_range(10, 2).unwrap(), b"");
+
+    let mut input = Input::memory_from("abcdef").with_prefix(vec![]);
+    assert_eq!(input.read_range(0, 1).unwrap_err().kind(), io::ErrorKind::Unsupported);
+}
+
+#[cfg(unix)]
+#[test]
+fn file_no_follow_rejects_symlinks() {
+    let dir = common::TempDir::new();
+    let target = dir.join("target");
+    let link = dir.join("link");
+    fs::write(&target, "data").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    assert!(Input::file_no_follow(&link).is_err());
+    assert!(Input::file_no_follow(&target).is_ok());
+    let mut s = String::new();
+    Input::file(&link).unwrap().read_to_string(&mut s).unwrap();
+    assert_eq!(s, "data");
+}
+
+#[test]
+fn normalize_crlf_split_across_reads() {
+    let input = Scripted::input(vec![Ok(b"a\r"), Ok(b"\nb\r\n"), Ok(b"c\rd")]);
+    assert_eq!(read_string(input.normalize_crlf()), "a\nb\nc\rd");
+}
+
+#[test]
+fn normalize_crlf_keeps_line_across_errors() {
+    let error = io::Error::other("flaky");
+    let mut input = Scripted::input(vec![Ok(b"a\r"), Err(error), Ok(b"\nb")]).normalize_crlf();
+    assert_eq!(input.read(&mut [0; 16]).unwrap_err().kind(), io::ErrorKind::Other);
+    assert_eq!(read_string(input), "a\nb");
+}
+
+#[test]
+fn trim_line_ends() {
+    assert_eq!(read_string(Input::memory_from("a  \nb\t\n").trim_line_ends()), "a\nb\n");
+    assert_eq!(read_string(Input::memory_from(" x \t\r\n\ny ").trim_line_ends()), " x\r\n\ny");
+}
+
+#[test]
+fn trim_line_ends_keeps_line_across_errors() {
+    let error = io::Error::other("flaky");
+    let mut input = Scripted::input(vec![Ok(b"a "), Err(error), Ok(b" \nb\t")]).trim_line_ends();
+    assert_eq!(input.read(&mut [0; 16]).unwrap_err().kind(), io::ErrorKind::Other);
+    assert_eq!(read_string(input), "a\nb");
+}
+
+#[test]
+fn max_read_size_caps_reads() {
+    let mut input = Input::memory_from(vec![1; 100]).max_read_size(16);
+    let mut buf = [0; 1024];
+    let mut total = 0;
+    loop {
+        let n = input.read(&mut buf).unwrap();
+        assert!(n <= 16);
+        if n == 0 {
+            break;
+        }
+        total += n;
+    }
+    assert_eq!(total, 100);
+}
+
+#[test]
+#[should_panic(expected = "maximum read size must be non-zero")]
+fn max_read_size_zero_panics() {
+    let _ = Input::memory().max_read_size(0);
+}
+
+#[test]
+fn kind_per_constructor() {
+    let dir = common::TempDir::new();
+    fs::write(dir.join("in"), "data").unwrap();
+    assert_eq!(Input::stdin().kind(), BufferKind::Standard);
+    assert_eq!(Input::memory().kind(), BufferKind::Memory);
+    assert_eq!(Input::memory_from("data").kind(), BufferKind::Memory);
+    assert_eq!(Input::file(dir.join("in")).unwrap().kind(), BufferKind::File);
+    assert_eq!(Input::from_arg(Some("-")).unwrap().kind(), BufferKind::Standard);
+    assert_eq!(Input::memory().trim_line_ends().kind(), BufferKind::Custom);
+}
+
+#[test]
+fn running_hash_digest_mid_stream() {
+    let (mut input, handle) = Input::memory_from("abc").running_hash(HashAlgorithm::Sha256);
+    let mut buf = [0; 2];
+    input.read_exact(&mut buf).unwrap();
+    let ab = common::hex("fb8e20fc2e4c3f248c60c39bd652f3c1347298bb977b8b4d5903b85055620603");
+    assert_eq!(input.current_digest().unwrap(), ab);
+    input.read_exact(&mut buf[..1]).unwrap();
+    let abc = common::hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(input.current_digest().unwrap(), abc);
+    assert_eq!(handle.current_digest(), abc);
+    assert_eq!(Input::memory().current_digest(), None);
+}
+
+#[cfg(feature = "ascii85")]
+#[test]
+fn ascii85_decodes() {
+    assert_eq!(read_string(Input::memory_from("<~87cURDZ~>").ascii85()), "Hello");
+    assert_eq!(read_string(Input::memory_from("87cUR DZ").ascii85()), "Hello");
+    assert_eq!(read_string(Input::memory_from("<~z87cURDZ~>ignored").ascii85()), "\0\0\0\0Hello");
+    // Groups split across reads of the encoded input.
+    let input = Scripted::input(vec![Ok(b"<~87c"), Ok(b"URD"), Ok(b"Z~>")]);
+    assert_eq!(read_string(input.ascii85()), "Hello");
+}
+
+#[test]
+fn read_to_end_checked_read_error() {
+    let mut data = vec![];
+    assert_eq!(Input::memory_from("ok").read_to_end_checked(&mut data).unwrap(), 2);
+    let mut input = Scripted::input(vec![Ok(b"ab"), Err(io::Error::other("gone"))]);
+    match input.read_to_end_checked(&mut data).unwrap_err() {
+        BufferError::Read { source } => assert_eq!(source.to_string(), "gone"),
+        other => panic!("expected a read error, got {:?}", other),
+    }
+    assert_eq!(data, b"okab");
+}
+
+#[test]
+fn with_cancel_stops_reading() {
+    let token = Arc::new(AtomicBool::new(false));
+    let mut input = Input::memory_from("abcdef").max_read_size(2).with_cancel(token.clone());
+    let mut buf = [0; 4];
+    assert_eq!(input.read(&mut buf).unwrap(), 2);
+    token.store(true, Ordering::SeqCst);
+    let err = input.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(err.get_ref().is_some_and(|e| e.is::<Cancelled>()));
+}
+
+#[test]
+fn with_deadline_bounds_reading() {
+    let past = Instant::now() - Duration::from_millis(1);
+    let err = Input::memory_from("abc").with_deadline(past).read(&mut [0; 4]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    let future = Instant::now() + Duration::from_secs(60);
+    assert_eq!(read_string(Input::memory_from("abc").with_deadline(future)), "abc");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_at_keeps_position() {
+    let dir = common::TempDir::new();
+    let path = dir.join("in");
+    fs::write(&path, "abcdef").unwrap();
+    for mut input in [Input::file(&path).unwrap(), Input::memory_from("abcdef")] {
+        let mut buf = [0; 1];
+        input.read_exact(&mut buf).unwrap();
+        let mut buf = [0; 3];
+        assert_eq!(input.read_at(&mut buf, 2).unwrap(), 3);
+        assert_eq!(&buf, b"cde");
+        assert_eq!(read_string(input), "bcdef");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn file_rejects_directories() {
+    let dir = common::TempDir::new();
+    for err in [Input::file(dir.path()).err(), Input::from_arg(dir.path().to_str()).err()] {
+        let err = err.unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "path is a directory");
+    }
+}
+
+#[test]
+fn as_stdin() {
+    assert!(Input::stdin().as_stdin().is_some());
+    assert!(Input::memory().as_stdin().is_none());
+}
+
+#[test]
+fn memory_from_with_eof() {
+    let mut input = Input::memory_from_with_eof("abc", EofBehavior::WouldBlock);
+    let mut buf = [0; 8];
+    assert_eq!(input.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"abc");
+    for _ in 0..2 {
+        assert_eq!(input.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    let mut input = Input::memory_from_with_eof("", EofBehavior::Error(io::ErrorKind::BrokenPipe));
+    assert_eq!(input.read(&mut buf).unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn read_frame_truncated() {
+    let mut input = Input::memory_from(b"\0\0\0\x02ok\0\0\0\x0aabcd".to_vec());
+    assert_eq!(input.read_frame().unwrap(), b"ok");
+    let err = input.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "truncated frame: 4/10 bytes");
+    assert_eq!(input.remaining_bytes().unwrap(), b"");
+
+    // The bytes after the last complete frame can be inspected instead.
+    let mut input = Input::memory_from(b"\0\0\0\x02ok\0\0".to_vec());
+    assert_eq!(input.read_frame().unwrap(), b"ok");
+    assert_eq!(input.remaining_bytes().unwrap(), b"\0\0");
+}
+
+#[test]
+fn remaining_slice() {
+    let mut input = Input::memory_from("abcdef");
+    input.read_exact(&mut [0; 2]).unwrap();
+    assert_eq!(input.remaining_slice(), Some(&b"cdef"[..]));
+    input.drain().unwrap();
+    assert_eq!(input.remaining_slice(), Some(&b""[..]));
+    assert_eq!(Input::stdin().remaining_slice(), None);
+}
+
+#[test]
+fn map_bytes() {
+    let input = Input::memory_from("abc").map_bytes(|b| b.to_ascii_uppercase());
+    assert_eq!(read_string(input), "ABC");
+}
+
+#[test]
+fn known_length() {
+    let dir = common::TempDir::new();
+    let path = dir.join("in");
+    fs::write(&path, "abcdef").unwrap();
+    let mut input = Input::file(&path).unwrap();
+    input.read_exact(&mut [0; 2]).unwrap();
+    assert_eq!(input.known_length(), Some(6));
+    assert_eq!(read_string(input), "cdef");
+
+    let mut input = Input::file(&path).unwrap().map_bytes(|b| b);
+    assert_eq!(input.known_length(), None);
+    assert_eq!(read_string(input), "abcdef");
+
+    // Pipes are files, but can't seek.
+    let (mut input, mut output) = wbuf::pipe().unwrap();
+    output.write_all(b"piped").unwrap();
+    drop(output);
+    assert_eq!(input.known_length(), None);
+    assert_eq!(read_string(input), "piped");
+}
+
+#[test]
+fn round_robin_alternates() {
+    let inputs = vec![Input::memory_from("AAAA"), Input::memory_from("BB")];
+    let mut input = Input::round_robin(inputs);
+    let mut reads = vec![];
+    let mut buf = [0; 1];
+    while input.read(&mut buf).unwrap() == 1 {
+        reads.push(buf[0]);
+    }
+    assert_eq!(reads, b"ABABAA");
+}
+
+#[cfg(unix)]
+#[test]
+fn terminal_guard_restores_settings() {
+    use std::io::IsTerminal;
+    use std::process::{Command, Stdio};
+
+    let err = Input::memory().terminal_guard().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    if !io::stdin().is_terminal() {
+        assert!(Input::stdin().terminal_guard().is_err());
+        return;
+    }
+    // `stty` works on the terminal it inherits as stdin.
+    let stty = |arg: &str| {
+        let out = Command::new("stty").arg(arg).stdin(Stdio::inherit()).output().unwrap();
+        assert!(out.status.success());
+        out.stdout
+    };
+    let before = stty("-g");
+    let guard = Input::stdin().terminal_guard().unwrap();
+    stty("-echo");
+    assert_ne!(stty("-g"), before);
+    drop(guard);
+    assert_eq!(stty("-g"), before);
+}
+
+#[test]
+fn read_until_seq() {
+    let request = "GET / HTTP/1.1\r\nHost: x\r\n\r\nbody";
+    for mut input in [Input::memory_from(request), Input::memory_from(request).map_bytes(|b| b)] {
+        let mut head = vec![];
+        assert_eq!(input.read_until_seq(b"\r\n\r\n", &mut head).unwrap(), 27);
+        assert_eq!(head, b"GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        let mut rest = vec![];
+        assert_eq!(input.read_until_seq(b"\r\n\r\n", &mut rest).unwrap(), 4);
+        assert_eq!(rest, b"body");
+    }
+}
+
+#[test]
+fn drain() {
+    let data = vec![0; 1000];
+    for mut input in [Input::memory_from(data.clone()), Input::memory_from(data).map_bytes(|b| b)] {
+        assert_eq!(input.drain().unwrap(), 1000);
+        assert_eq!(input.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(input.drain().unwrap(), 0);
+    }
+}
+
+#[test]
+fn lines_auto_detects_terminator() {
+    for text in ["one\ntwo\nthree", "one\r\ntwo\r\nthree\r\n", "one\rtwo\rthree\r"] {
+        let mut input = Input::memory_from(text);
+        let lines: Vec<_> = input.lines_auto().collect::<io::Result<_>>().unwrap();
+        assert_eq!(lines, ["one", "two", "three"], "{:?}", text);
+    }
+    // Only the first terminator found splits lines.
+    let mut input = Input::memory_from("a\nb\r\nc");
+    let lines: Vec<_> = input.lines_auto().collect::<io::Result<_>>().unwrap();
+    assert_eq!(lines, ["a", "b\r", "c"]);
+}
+
+#[test]
+fn max_line_len() {
+    use std::io::BufRead;
+
+    let mut input = io::BufReader::new(Input::memory_from("short\nok\n").max_line_len(1000));
+    let mut line = String::new();
+    input.read_line(&mut line).unwrap();
+    assert_eq!(line, "short\n");
+
+    let long = format!("{}\n", "x".repeat(2000));
+    let mut input = Input::memory_from(long).max_line_len(1000);
+    let mut data = vec![];
+    let err = input.read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "line too long");
+    assert_eq!(data.len(), 1000);
+    assert!(input.read(&mut [0; 4]).is_err());
+}
+
+#[test]
+fn stdin_if_piped() {
+    use std::process::{Command, Stdio};
+
+    // Runs this test alone in a child, with stdin redirected from a pipe.
+    if std::env::var_os("WBUF_PIPED_STDIN").is_some() {
+        assert_eq!(read_string(Input::stdin_if_piped().unwrap()), "piped");
+        return;
+    }
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "stdin_if_piped", "--test-threads=1"])
+        .env("WBUF_PIPED_STDIN", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"piped").unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn read_until_seq_keeps_bytes_past_the_delimiter() {
+    // The whole input comes in a single read, which only buffering can take.
+    let mut input = Scripted::input(vec![Ok(b"head\r\n\r\nbody")]);
+    let mut head = vec![];
+    assert_eq!(input.read_until_seq(b"\r\n\r\n", &mut head).unwrap(), 8);
+    assert_eq!(head, b"head\r\n\r\n");
+    assert_eq!(input.kind(), BufferKind::Custom);
+    assert_eq!(read_string(input), "body");
+
+    // Delimiters split across the reads of a buffered input.
+    let mut input = Input::memory_from("a--b--c").max_read_size(3);
+    let mut parts = vec![];
+    for _ in 0..3 {
+        let mut part = vec![];
+        input.read_until_seq(b"--", &mut part).unwrap();
+        parts.push(part);
+    }
+    assert_eq!(parts, [&b"a--"[..], b"b--", b"c"]);
+}
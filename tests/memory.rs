@@ -0,0 +1,38 @@
+//! The `alloc`-only memory buffers of the `memory` module, also available without `std`.
+
+use std::path::Path;
+use std::process::Command;
+
+use wbuf::memory::{MemInput, MemOutput, MemRead, MemWrite};
+
+#[test]
+fn mem_output_then_input() {
+    let mut out = MemOutput::new();
+    assert_eq!(out.write(b"hello "), 6);
+    assert_eq!(out.write(b"world"), 5);
+    assert_eq!(out.as_slice(), b"hello world");
+
+    let data = out.into_vec();
+    let mut input = MemInput::new(&data);
+    let mut buf = [0; 8];
+    assert_eq!(input.read(&mut buf), 8);
+    assert_eq!(&buf, b"hello wo");
+    assert_eq!(input.position(), 8);
+    assert_eq!(input.remaining(), b"rld");
+    assert_eq!(input.read(&mut buf), 3);
+    assert_eq!(input.read(&mut buf), 0);
+}
+
+/// Builds and tests the `tests/no_std` crate, which uses the `memory` module from a `#![no_std]`
+/// crate, with the `std` feature of this crate disabled.
+#[test]
+fn no_std_crate() {
+    let status = Command::new(env!("CARGO"))
+        .args(["test", "--quiet", "--offline", "--manifest-path"])
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/no_std/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
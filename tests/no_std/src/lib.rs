@@ -0,0 +1,38 @@
+//! Uses the `memory` module of `wbuf` from a `no_std` crate, with `std` disabled in `wbuf`.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use wbuf::memory::{MemInput, MemOutput, MemRead, MemWrite};
+
+/// Writes `parts` to a memory output, then reads them back from a memory input, `chunk` bytes at
+/// a time.
+pub fn round_trip(parts: &[&[u8]], chunk: usize) -> Vec<u8> {
+    let mut out = MemOutput::new();
+    for part in parts {
+        assert_eq!(out.write(part), part.len());
+    }
+    let data = out.into_vec();
+    let mut input = MemInput::new(&data);
+    let mut read = Vec::new();
+    let mut buf = alloc::vec![0; chunk];
+    loop {
+        match input.read(&mut buf) {
+            0 => break,
+            n => read.extend_from_slice(&buf[..n]),
+        }
+    }
+    assert!(input.remaining().is_empty());
+    read
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn round_trip() {
+        assert_eq!(super::round_trip(&[b"hello ", b"world"], 4), b"hello world");
+        assert_eq!(super::round_trip(&[], 4), b"");
+    }
+}
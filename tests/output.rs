@@ -0,0 +1,877 @@
+//! Output constructors and wrappers.
+
+mod common;
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use wbuf::{
+    AlignedBuf, BufferError, BufferKind, HashAlgorithm, Input, InputOutput, MemFs, Output,
+    OutputAdapter,
+};
+
+#[test]
+fn is_flushed_buffered() {
+    let mut out = Output::memory().smart_buffered(16, 64);
+    assert!(out.is_flushed());
+    out.write_all(b"small").unwrap();
+    assert!(!out.is_flushed());
+    out.flush().unwrap();
+    assert!(out.is_flushed());
+    assert!(Output::memory().is_flushed());
+}
+
+#[test]
+fn is_flushed_uniq_partial_line() {
+    let mut out = Output::memory().uniq();
+    out.write_all(b"line\npartial").unwrap();
+    assert!(!out.is_flushed());
+    out.finish().unwrap();
+    assert!(out.is_flushed());
+}
+
+fn sanitized(writes: &[&[u8]]) -> Vec<u8> {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.sanitize_terminal();
+    for data in writes {
+        out.write_all(data).unwrap();
+    }
+    out.finish().unwrap();
+    assert!(out.is_flushed());
+    let data = data.lock().unwrap().clone();
+    data
+}
+
+#[test]
+fn sanitize_terminal_control_bytes() {
+    assert_eq!(sanitized(&[b"\x1b[31mred\x1b[0m\tx\x7f\n"]), b"^[[31mred^[[0m\tx^?\n");
+}
+
+#[test]
+fn sanitize_terminal_c1() {
+    // Single byte CSI, and the same control code as a UTF-8 encoded character.
+    assert_eq!(sanitized(&[b"a\x9b2Jb"]), b"aM-^[2Jb");
+    assert_eq!(sanitized(&["a\u{9b}2Jb".as_bytes()]), b"aM-^[2Jb");
+    // Printable non-ASCII text passes through, even when split across writes.
+    assert_eq!(sanitized(&["é€".as_bytes()]), "é€".as_bytes());
+    let euro = "€".as_bytes();
+    assert_eq!(sanitized(&[&euro[..1], &euro[1..2], &euro[2..]]), euro);
+    // A sequence left incomplete at the end is replaced as a whole.
+    assert_eq!(sanitized(&[b"x\xe2\x82"]), "x\u{FFFD}".as_bytes());
+    assert_eq!(sanitized(&[b"x\xf0", b"\x9f"]), "x\u{FFFD}".as_bytes());
+}
+
+#[cfg(windows)]
+#[test]
+fn binary_mode_writes_single_newline_byte() {
+    let dir = common::TempDir::new();
+    let path = dir.join("binary.txt");
+    let mut out = Output::file_binary(&path).unwrap();
+    out.write_all(b"\n").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"\n");
+
+    let mut out = Output::file(&path).unwrap();
+    out.write_all(b"\n").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"\n");
+}
+
+#[test]
+fn text_mode_translates_newlines_on_windows_only() {
+    let dir = common::TempDir::new();
+    let path = dir.join("text.txt");
+    let mut out = Output::file_text(&path).unwrap();
+    out.write_all(b"a\nb\n").unwrap();
+    drop(out);
+    let expected: &[u8] = if cfg!(windows) { b"a\r\nb\r\n" } else { b"a\nb\n" };
+    assert_eq!(fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn binary_mode_keeps_bytes() {
+    let dir = common::TempDir::new();
+    let path = dir.join("binary.bin");
+    let data: Vec<u8> = (0..=255).collect();
+    let mut out = Output::file_binary(&path).unwrap();
+    out.write_all(&data).unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), data);
+}
+
+fn read_string(mut input: Input) -> String {
+    let mut s = String::new();
+    input.read_to_string(&mut s).unwrap();
+    s
+}
+
+#[test]
+fn reopen_file_for_reading() {
+    let dir = common::TempDir::new();
+    let mut out = Output::file(dir.join("xyz")).unwrap();
+    out.write_all(b"xyz").unwrap();
+    assert_eq!(read_string(out.reopen_for_reading().unwrap()), "xyz");
+}
+
+#[test]
+fn reopen_memory_for_reading() {
+    let mut out = Output::memory();
+    out.write_all(b"in memory").unwrap();
+    assert_eq!(read_string(out.reopen_for_reading().unwrap()), "in memory");
+}
+
+#[test]
+fn reopen_stdout_for_reading_fails() {
+    let err = Output::stdout().reopen_for_reading().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
+
+/// Writer recording the size of every write it receives.
+struct Tracking {
+    data: Arc<Mutex<Vec<u8>>>,
+    writes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Write for Tracking {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.lock().unwrap().extend_from_slice(buf);
+        self.writes.lock().unwrap().push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn smart_buffered_bypasses_large_writes() {
+    let data = Arc::new(Mutex::new(vec![]));
+    let writes = Arc::new(Mutex::new(vec![]));
+    let inner = Output::Custom(Box::new(Tracking { data: data.clone(), writes: writes.clone() }));
+    let mut out = inner.smart_buffered(16, 64);
+
+    let large = vec![b'x'; 100];
+    out.write_all(b"ab").unwrap();
+    out.write_all(b"cd").unwrap();
+    assert!(writes.lock().unwrap().is_empty());
+    out.write_all(&large).unwrap();
+    out.write_all(b"ef").unwrap();
+    out.flush().unwrap();
+
+    let mut expected = b"abcd".to_vec();
+    expected.extend_from_slice(&large);
+    expected.extend_from_slice(b"ef");
+    assert_eq!(*data.lock().unwrap(), expected);
+    // The small writes were coalesced, and the large one went through in a single call.
+    assert_eq!(*writes.lock().unwrap(), [4, 100, 2]);
+}
+
+#[test]
+fn shared_output_keeps_lines_whole() {
+    let (inner, data) = Output::shared_vec();
+    let shared = inner.shared();
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let mut out = shared.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    writeln!(out, "thread {} line {} {}", t, i, "-".repeat(64)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let data = String::from_utf8(data.lock().unwrap().clone()).unwrap();
+    let mut lines: Vec<_> = data.lines().collect();
+    assert_eq!(lines.len(), 400);
+    lines.sort_unstable();
+    let mut expected: Vec<_> = (0..4)
+        .flat_map(|t| (0..100).map(move |i| format!("thread {} line {} {}", t, i, "-".repeat(64))))
+        .collect();
+    expected.sort_unstable();
+    assert_eq!(lines, expected);
+}
+
+/// Writer accepting at most 3 bytes per call, and failing once `left` bytes have been written.
+struct FailAfter {
+    left: usize,
+}
+
+impl Write for FailAfter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "sink closed"));
+        }
+        let n = buf.len().min(self.left).min(3);
+        self.left -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn flush_counted_reports_partial_flush() {
+    let mut out = Output::Custom(Box::new(FailAfter { left: 10 })).smart_buffered(32, 64);
+    out.write_all(b"0123456789abcdef").unwrap();
+    let (written, err) = out.flush_counted().unwrap_err();
+    assert_eq!(written, 10);
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert!(!out.is_flushed());
+
+    assert_eq!(Output::memory().flush_counted().unwrap(), 0);
+}
+
+#[test]
+fn close_surfaces_flush_errors() {
+    let mut out = Output::memory();
+    out.write_all(b"data").unwrap();
+    out.close().unwrap();
+    InputOutput::memory().close().unwrap();
+
+    // The buffered byte only reaches the failing sink when closing.
+    let mut out = Output::Custom(Box::new(FailAfter { left: 0 })).smart_buffered(32, 64);
+    out.write_all(b"x").unwrap();
+    assert_eq!(out.close().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn file_with_extension() {
+    let dir = common::TempDir::new();
+    let (mut out, path) = Output::file_with_extension(dir.join("foo"), "json").unwrap();
+    assert_eq!(path, dir.join("foo.json"));
+    out.write_all(b"{}").unwrap();
+    drop(out);
+    assert_eq!(fs::read(dir.join("foo.json")).unwrap(), b"{}");
+
+    let (_, path) = Output::file_with_extension(dir.join("foo.txt"), "json").unwrap();
+    assert_eq!(path, dir.join("foo.json"));
+    let (_, path) = Output::file_with_extension(dir.join("bar.txt"), ".json").unwrap();
+    assert_eq!(path, dir.join("bar.json"));
+    assert!(!dir.join("foo.txt").exists());
+}
+
+#[test]
+fn ensure_trailing_newline() {
+    let finished = |writes: &[&[u8]]| {
+        let (inner, data) = Output::shared_vec();
+        let mut out = inner.ensure_trailing_newline();
+        for w in writes {
+            out.write_all(w).unwrap();
+        }
+        out.finish().unwrap();
+        out.finish().unwrap();
+        let data = data.lock().unwrap().clone();
+        data
+    };
+    assert_eq!(finished(&[b"one\n", b"two"]), b"one\ntwo\n");
+    assert_eq!(finished(&[b"one\ntwo\n"]), b"one\ntwo\n");
+    assert_eq!(finished(&[b"one\n", b""]), b"one\n");
+    assert_eq!(finished(&[]), b"");
+}
+
+#[test]
+fn with_capture_tees_into_memory() {
+    let dir = common::TempDir::new();
+    let path = dir.join("out.txt");
+    let (mut out, capture) = Output::file(&path).unwrap().with_capture();
+    out.write_all(b"first line\n").unwrap();
+    out.write_all(b"second line\n").unwrap();
+    out.close().unwrap();
+    assert_eq!(capture.contents(), b"first line\nsecond line\n");
+    assert_eq!(fs::read(&path).unwrap(), capture.contents());
+}
+
+#[test]
+fn content_eq() {
+    let mut a = Output::memory();
+    let mut b = Output::memory();
+    a.write_all(b"same").unwrap();
+    b.write_all(b"same").unwrap();
+    assert!(a.content_eq(&b));
+    b.write_all(b"!").unwrap();
+    assert!(!a.content_eq(&b));
+    assert!(!Output::stdout().content_eq(&Output::stdout()));
+
+    assert!(Input::memory_from("abc").content_eq(&Input::memory_from("abc")));
+    assert!(!Input::memory_from("abc").content_eq(&Input::memory_from("abd")));
+}
+
+#[derive(Default)]
+struct FlushCounts {
+    written: usize,
+    flushes: usize,
+    flushed: usize,
+}
+
+/// Writer counting how many times it was flushed, and how many bytes it got before the last flush.
+#[derive(Clone, Default)]
+struct FlushCounter {
+    counts: Arc<Mutex<FlushCounts>>,
+}
+
+impl FlushCounter {
+    /// Returns the number of flushes, and the number of bytes written before the last one.
+    fn flushes(&self) -> (usize, usize) {
+        let counts = self.counts.lock().unwrap();
+        (counts.flushes, counts.flushed)
+    }
+}
+
+impl Write for FlushCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.counts.lock().unwrap().written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut counts = self.counts.lock().unwrap();
+        counts.flushes += 1;
+        counts.flushed = counts.written;
+        Ok(())
+    }
+}
+
+#[test]
+fn unbuffered_flushes_every_write() {
+    let counter = FlushCounter::default();
+    let mut out = Output::Custom(Box::new(counter.clone())).unbuffered();
+    out.write_all(b"one").unwrap();
+    assert_eq!(counter.flushes(), (1, 3));
+    out.write_all(b"two").unwrap();
+    assert_eq!(counter.flushes(), (2, 6));
+
+    // Bytes held by a buffer underneath are written out as well.
+    let counter = FlushCounter::default();
+    let inner = Output::Custom(Box::new(counter.clone())).smart_buffered(32, 64);
+    let mut out = inner.unbuffered();
+    out.write_all(b"small").unwrap();
+    assert_eq!(counter.flushes(), (1, 5));
+}
+
+#[cfg(unix)]
+#[test]
+fn checkpoint_makes_writes_visible() {
+    let dir = common::TempDir::new();
+    let path = dir.join("journal");
+    let mut out = Output::file(&path).unwrap().smart_buffered(32, 64);
+    out.write_all(b"entry 1\n").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"");
+    out.checkpoint().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"entry 1\n");
+
+    let mut out = Output::memory();
+    out.write_all(b"entry").unwrap();
+    out.checkpoint().unwrap();
+}
+
+/// Adapter counting the syncs asked of it, in place of the file it would hold.
+struct SyncCounter {
+    inner: Output,
+    syncs: Arc<Mutex<Vec<bool>>>,
+}
+
+impl Write for SyncCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl OutputAdapter for SyncCounter {
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn inner(&self) -> &Output {
+        &self.inner
+    }
+
+    fn inner_mut(&mut self) -> &mut Output {
+        &mut self.inner
+    }
+
+    fn sync(&mut self, data_only: bool) -> io::Result<()> {
+        self.syncs.lock().unwrap().push(data_only);
+        Ok(())
+    }
+}
+
+#[test]
+fn checkpoint_and_close_sync_through_adapters() {
+    let syncs = Arc::new(Mutex::new(vec![]));
+    let counter = SyncCounter { inner: Output::memory(), syncs: syncs.clone() };
+    let mut out = Output::Adapter(Box::new(counter)).smart_buffered(16, 64);
+    out.checkpoint().unwrap();
+    out.close().unwrap();
+    assert_eq!(*syncs.lock().unwrap(), [true, false]);
+}
+
+#[test]
+fn indented_lines() {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.indented(1, "  ");
+    out.write_all(b"a\nb\n").unwrap();
+    assert_eq!(*data.lock().unwrap(), b"  a\n  b\n");
+}
+
+#[test]
+fn set_indent_flushes_buffered_lines() {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.indented(1, "  ").smart_buffered(4, 16);
+    out.write_all(b"a\n").unwrap();
+    out.set_indent(2).unwrap();
+    out.write_all(b"b\n").unwrap();
+    out.flush().unwrap();
+    assert_eq!(*data.lock().unwrap(), b"  a\n    b\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn file_no_follow_rejects_symlinks() {
+    let dir = common::TempDir::new();
+    let target = dir.join("target");
+    let link = dir.join("link");
+    fs::write(&target, "").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    assert!(Output::file_no_follow(&link).is_err());
+    Output::file(&link).unwrap().write_all(b"data").unwrap();
+    assert_eq!(fs::read(&target).unwrap(), b"data");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn file_direct_aligned_round_trip() {
+    let dir = common::TempDir::new();
+    let path = dir.join("direct");
+    let mut out = match Output::file_direct(&path) {
+        Ok(out) => out,
+        // Some filesystems, like older versions of tmpfs, don't support direct IO.
+        Err(e) if e.raw_os_error() == Some(22) => return,
+        Err(e) => panic!("{}", e),
+    };
+    let mut data = AlignedBuf::new(4096, 4096);
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    out.write_all(&data).unwrap();
+    drop(out);
+
+    let mut input = Input::file_direct(&path).unwrap();
+    let mut read = AlignedBuf::new(4096, 4096);
+    input.read_exact(&mut read).unwrap();
+    assert_eq!(&read[..], &data[..]);
+}
+
+#[test]
+fn kind_per_constructor() {
+    let dir = common::TempDir::new();
+    assert_eq!(Output::stdout().kind(), BufferKind::Standard);
+    assert_eq!(Output::stderr().kind(), BufferKind::Standard);
+    assert_eq!(Output::memory().kind(), BufferKind::Memory);
+    assert_eq!(Output::from_vec(vec![]).kind(), BufferKind::Memory);
+    assert_eq!(Output::shared_vec().0.kind(), BufferKind::Memory);
+    assert_eq!(Output::file(dir.join("a")).unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::file_binary(dir.join("b")).unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::file_text(dir.join("c")).unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::lazy_file(dir.join("d")).kind(), BufferKind::File);
+    assert_eq!(Output::file_append_locked(dir.join("e")).unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::file_in(&MemFs::new(), "f").unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::temp().unwrap().kind(), BufferKind::File);
+    assert_eq!(Output::memory().uniq().smart_buffered(4, 16).kind(), BufferKind::Memory);
+    assert_eq!(Output::Custom(Box::new(io::sink())).kind(), BufferKind::Custom);
+}
+
+#[test]
+fn running_hash_digest_mid_stream() {
+    let (mut out, handle) = Output::memory().running_hash(HashAlgorithm::Sha256);
+    out.write_all(b"ab").unwrap();
+    let ab = common::hex("fb8e20fc2e4c3f248c60c39bd652f3c1347298bb977b8b4d5903b85055620603");
+    assert_eq!(out.current_digest().unwrap(), ab);
+    out.write_all(b"c").unwrap();
+    let abc = common::hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(out.current_digest().unwrap(), abc);
+    assert_eq!(handle.current_digest(), abc);
+    assert_eq!(Output::memory().current_digest(), None);
+}
+
+#[test]
+fn running_hash_under_buffer() {
+    let (out, _) = Output::memory().running_hash(HashAlgorithm::Crc32c);
+    let mut out = out.smart_buffered(16, 64);
+    out.write_all(b"123456789").unwrap();
+    assert_eq!(out.current_digest().unwrap(), [0, 0, 0, 0]);
+    out.flush().unwrap();
+    assert_eq!(out.current_digest().unwrap(), 0xe306_9283u32.to_be_bytes());
+}
+
+/// Returns the digest of `data` written in one go.
+fn digest(algo: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    let (mut out, _) = Output::memory().running_hash(algo);
+    out.write_all(data).unwrap();
+    out.current_digest().unwrap()
+}
+
+#[test]
+fn sha256_known_answers() {
+    let vectors: [(&[u8], &str); 4] = [
+        (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+        (&[b'a'; 1000], "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"),
+    ];
+    for (data, expected) in vectors.iter() {
+        assert_eq!(digest(HashAlgorithm::Sha256, data), common::hex(expected));
+    }
+}
+
+#[test]
+fn crc32c_known_answers() {
+    let vectors: [(&[u8], u32); 3] =
+        [(b"", 0), (b"123456789", 0xe306_9283), (&[0; 32], 0x8a91_36aa)];
+    for (data, expected) in vectors.iter() {
+        assert_eq!(digest(HashAlgorithm::Crc32c, data), expected.to_be_bytes());
+    }
+}
+
+/// Returns what `with_crc_footer` writes for `data`.
+fn with_crc_footer(data: &[u8]) -> Vec<u8> {
+    let (inner, written) = Output::shared_vec();
+    let mut out = inner.with_crc_footer();
+    out.write_all(data).unwrap();
+    out.finish().unwrap();
+    let written = written.lock().unwrap().clone();
+    written
+}
+
+#[test]
+fn crc_footer_round_trip() {
+    let framed = with_crc_footer(b"123456789");
+    assert_eq!(&framed[9..], 0xe306_9283u32.to_le_bytes());
+    let mut data = vec![];
+    Input::memory_from(framed).verify_crc_footer().read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"123456789");
+}
+
+#[test]
+fn crc_footer_detects_tampering() {
+    let mut framed = with_crc_footer(b"some payload");
+    framed[3] ^= 1;
+    let mut data = vec![];
+    let err = Input::memory_from(framed).verify_crc_footer().read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let err = Input::memory_from("abc").verify_crc_footer().read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn write_all_checked_write_error() {
+    let mut out = Output::Custom(Box::new(io::Cursor::new([0; 2])));
+    out.write_all_checked(b"ab").unwrap();
+    match out.write_all_checked(b"c").unwrap_err() {
+        BufferError::Write { source } => assert_eq!(source.kind(), io::ErrorKind::WriteZero),
+        other => panic!("expected a write error, got {:?}", other),
+    }
+}
+
+#[test]
+fn uniq_drops_repeated_lines() {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.uniq();
+    out.write_all(b"x\nx\ny\n").unwrap();
+    out.finish().unwrap();
+    assert_eq!(*data.lock().unwrap(), b"x\ny\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn write_at_keeps_position() {
+    let dir = common::TempDir::new();
+    let path = dir.join("out");
+    let mut out = Output::file(&path).unwrap();
+    out.write_all(b"abcdef").unwrap();
+    assert_eq!(out.write_at(b"XY", 3).unwrap(), 2);
+    out.write_all(b"g").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"abcXYfg");
+
+    let mut out = Output::memory();
+    out.write_all(b"abcdef").unwrap();
+    assert_eq!(out.write_at(b"XY", 3).unwrap(), 2);
+    out.write_all(b"g").unwrap();
+    assert_eq!(out.into_vec().unwrap(), b"abcXYfg");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn preallocate_extends_file() {
+    let dir = common::TempDir::new();
+    let path = dir.join("out");
+    let mut out = Output::file(&path).unwrap().smart_buffered(16, 64);
+    out.preallocate(1 << 20).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 1 << 20);
+    out.write_all(b"data").unwrap();
+    drop(out);
+    let data = fs::read(&path).unwrap();
+    assert_eq!(data.len(), 1 << 20);
+    assert_eq!(&data[..4], b"data");
+}
+
+#[test]
+fn as_stdout() {
+    assert!(Output::stdout().as_stdout().is_some());
+    assert!(Output::memory().as_stdout().is_none());
+    assert!(Output::stdout().uniq().as_stdout().is_none());
+}
+
+#[test]
+fn frames_round_trip() {
+    let frames: [&[u8]; 3] = [b"first", b"", b"third frame"];
+    let mut out = Output::memory();
+    for frame in frames {
+        out.write_frame(frame).unwrap();
+    }
+    let mut input = out.reopen_for_reading().unwrap();
+    for frame in frames {
+        assert_eq!(input.read_frame().unwrap(), frame);
+    }
+    assert_eq!(input.read_frame().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn seek_past_end_zero_fills() {
+    let mut out = Output::memory();
+    assert_eq!(out.seek(SeekFrom::Start(5)).unwrap(), 5);
+    out.write_all(b"X").unwrap();
+    assert_eq!(out.seek_relative(-3).unwrap(), 3);
+    assert_eq!(out.seek_end().unwrap(), 6);
+    assert_eq!(out.into_vec().unwrap(), b"\0\0\0\0\0X");
+}
+
+#[test]
+fn reset_discards_previous_writes() {
+    let mut out = Output::memory();
+    out.write_all(b"first").unwrap();
+    out.reset().unwrap();
+    out.write_all(b"second").unwrap();
+    assert_eq!(out.into_vec().unwrap(), b"second");
+
+    let dir = common::TempDir::new();
+    let path = dir.join("out");
+    let mut out = Output::file(&path).unwrap().smart_buffered(16, 64);
+    out.write_all(b"first line").unwrap();
+    out.reset().unwrap();
+    out.write_all(b"second").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"second");
+}
+
+#[test]
+fn wrapped_keeps_lines_within_width() {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.wrapped(20);
+    out.write_all(b"The quick brown fox jumps over the lazy dog, ").unwrap();
+    out.write_all(b"then an extraordinarily long word: ").unwrap();
+    out.write_all(b"pneumonoultramicroscopicsilicovolcanoconiosis.\nShort line.").unwrap();
+    out.finish().unwrap();
+    let text = String::from_utf8(data.lock().unwrap().clone()).unwrap();
+    assert!(text.lines().all(|line| line.chars().count() <= 20), "{:?}", text);
+    assert!(text.contains("\nShort line."));
+    let words = |s: &str| s.split_whitespace().collect::<String>();
+    assert_eq!(
+        words(&text),
+        words("The quick brown fox jumps over the lazy dog, then an extraordinarily long word: \
+               pneumonoultramicroscopicsilicovolcanoconiosis. Short line.")
+    );
+}
+
+/// Writer failing every write and flush.
+struct Failing;
+
+impl Write for Failing {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+#[test]
+fn broadcast_survives_failing_output() {
+    let (working, data) = Output::shared_vec();
+    let errors = Arc::new(Mutex::new(vec![]));
+    let errors2 = errors.clone();
+    let on_error = move |i, e: io::Error| errors2.lock().unwrap().push((i, e.kind()));
+    let mut out = Output::broadcast(vec![Output::Custom(Box::new(Failing)), working], on_error);
+    out.write_all(b"hello").unwrap();
+    out.flush().unwrap();
+    assert_eq!(*data.lock().unwrap(), b"hello");
+    let broken = (0, io::ErrorKind::BrokenPipe);
+    assert_eq!(*errors.lock().unwrap(), [broken, broken]);
+
+    let mut out = Output::broadcast(vec![Output::Custom(Box::new(Failing))], |_, _| {});
+    assert!(out.write_all(b"hello").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_append_locked_keeps_records_whole() {
+    let dir = common::TempDir::new();
+    let path = dir.join("log");
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut out = Output::file_append_locked(&path).unwrap();
+                for i in 0..100 {
+                    let record = format!("{}:{}:{}\n", t, i, "x".repeat(1000));
+                    out.write_all(record.as_bytes()).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let data = fs::read_to_string(&path).unwrap();
+    let mut lines: Vec<_> = data.lines().collect();
+    assert_eq!(lines.len(), 400);
+    assert!(lines.iter().all(|line| line.len() > 1000 && line.ends_with(&"x".repeat(1000))));
+    lines.sort_unstable();
+    lines.dedup_by_key(|line| line.split(':').take(2).collect::<Vec<_>>());
+    assert_eq!(lines.len(), 400);
+}
+
+#[test]
+fn lazy_file_opens_on_first_write() {
+    let dir = common::TempDir::new();
+    let path = dir.join("lazy");
+    let mut out = Output::lazy_file(&path);
+    out.flush().unwrap();
+    assert!(!path.exists());
+    drop(out);
+    assert!(!path.exists());
+
+    let mut out = Output::lazy_file(&path);
+    out.write_all(b"written").unwrap();
+    assert!(path.exists());
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"written");
+}
+
+#[test]
+fn table_aligns_columns() {
+    let (inner, data) = Output::shared_vec();
+    let mut out = inner.table();
+    out.write_all(b"a\tbb\nccc\td\n").unwrap();
+    out.finish().unwrap();
+    assert_eq!(*data.lock().unwrap(), b"a    bb\nccc  d\n");
+}
+
+#[test]
+fn from_vec_appends() {
+    let mut out = Output::from_vec(vec![1, 2, 3]);
+    out.write_all(&[4, 5]).unwrap();
+    assert_eq!(out.into_vec().unwrap(), [1, 2, 3, 4, 5]);
+    assert_eq!(Output::stdout().into_vec(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn set_nonblocking_pipe_would_block() {
+    let (mut input, mut output) = wbuf::pipe().unwrap();
+    output.set_nonblocking(true).unwrap();
+    let chunk = [b'x'; 4096];
+    let mut written = 0;
+    let err = loop {
+        match output.write(&chunk) {
+            Ok(n) => written += n,
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert!(written > 0);
+
+    output.set_nonblocking(false).unwrap();
+    drop(output);
+    let mut data = vec![];
+    input.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len(), written);
+}
+
+#[test]
+fn shared_vec_handle_sees_writes() {
+    let (mut out, data) = Output::shared_vec();
+    out.write_all(b"hello").unwrap();
+    assert_eq!(*data.lock().unwrap(), b"hello");
+    let handle = data.clone();
+    out.write_all(b" world").unwrap();
+    drop(out);
+    assert_eq!(*handle.lock().unwrap(), b"hello world");
+    assert_eq!(Arc::strong_count(&data), 2);
+}
+
+#[test]
+fn seek_end_and_relative_on_file() {
+    let dir = common::TempDir::new();
+    let path = dir.join("out");
+    let mut out = Output::file(&path).unwrap();
+    out.write_all(b"abcdef").unwrap();
+    assert_eq!(out.seek_relative(-4).unwrap(), 2);
+    out.write_all(b"X").unwrap();
+    assert_eq!(out.seek_end().unwrap(), 6);
+    out.write_all(b"g").unwrap();
+    drop(out);
+    assert_eq!(fs::read(&path).unwrap(), b"abXdefg");
+
+    let err = Output::stdout().seek_end().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn file_append_locked_reaches_its_file() {
+    let dir = common::TempDir::new();
+    let path = dir.join("log");
+    let mut out = Output::file_append_locked(&path).unwrap();
+    out.write_all(b"record\n").unwrap();
+    out.checkpoint().unwrap();
+    out.set_nonblocking(false).unwrap();
+    out.preallocate(4096).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 4096);
+    out.close().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn lazy_file_reaches_its_file_once_open() {
+    let dir = common::TempDir::new();
+    let path = dir.join("lazy");
+    let mut out = Output::lazy_file(&path);
+    out.checkpoint().unwrap();
+    assert!(!path.exists());
+    out.write_all(b"written").unwrap();
+    out.checkpoint().unwrap();
+    out.set_nonblocking(false).unwrap();
+    out.preallocate(4096).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 4096);
+    out.close().unwrap();
+    assert_eq!(&fs::read(&path).unwrap()[..7], b"written");
+}
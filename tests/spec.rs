@@ -0,0 +1,66 @@
+//! `BufferSpec` descriptors.
+
+mod common;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use wbuf::BufferSpec;
+
+#[test]
+fn file_specs_compare_by_path() {
+    let a = BufferSpec::from("out/a.txt");
+    assert_eq!(a, BufferSpec::File(PathBuf::from("out/a.txt")));
+    assert_eq!(a, a.clone());
+    assert_ne!(a, BufferSpec::from("out/b.txt"));
+    assert_eq!(BufferSpec::from("-"), BufferSpec::Stdin);
+}
+
+#[test]
+fn spec_as_map_key() {
+    let mut jobs = HashMap::new();
+    *jobs.entry(BufferSpec::from("a.txt")).or_insert(0) += 1;
+    *jobs.entry(BufferSpec::from("a.txt")).or_insert(0) += 1;
+    *jobs.entry(BufferSpec::Stdout).or_insert(0) += 1;
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[&BufferSpec::File(PathBuf::from("a.txt"))], 2);
+}
+
+#[test]
+fn open_file_spec() {
+    let dir = common::TempDir::new();
+    let spec = BufferSpec::File(dir.join("spec.txt"));
+    spec.open_output().unwrap().write_all(b"through a spec").unwrap();
+    let mut s = String::new();
+    spec.open_input().unwrap().read_to_string(&mut s).unwrap();
+    assert_eq!(s, "through a spec");
+    assert!(BufferSpec::Stdout.open_input().is_err());
+}
+
+#[test]
+fn specs_sort_stdio_then_files() {
+    let mut specs = vec![
+        BufferSpec::Memory,
+        BufferSpec::from("b.txt"),
+        BufferSpec::Stderr,
+        BufferSpec::from("a/z.txt"),
+        BufferSpec::Stdout,
+        BufferSpec::from("a.txt"),
+        BufferSpec::Stdin,
+    ];
+    specs.sort();
+    assert_eq!(
+        specs,
+        [
+            BufferSpec::Stdin,
+            BufferSpec::Stdout,
+            BufferSpec::Stderr,
+            // Paths compare component by component, so "a" sorts before "a.txt".
+            BufferSpec::from("a/z.txt"),
+            BufferSpec::from("a.txt"),
+            BufferSpec::from("b.txt"),
+            BufferSpec::Memory,
+        ]
+    );
+}